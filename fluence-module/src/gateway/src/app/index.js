@@ -73,9 +73,48 @@ export default async function (server) {
 
   
 
+  const proofRequest = Type.Object({
+    leaf: Type.String(),
+    siblings: Type.Array(Type.String()),
+    path_bits: Type.Array(Type.Boolean()),
+    root: Type.String({ default: "" }),
+    circuit_id: Type.String({ default: "" }),
+  });
+
+  const g1Coordinates = Type.Object({ x: Type.String(), y: Type.String() });
+  const fq2Coordinates = Type.Object({ c0: Type.String(), c1: Type.String() });
+
+  const proofResponse = Type.Object({
+    envelope: Type.Object({
+      version: Type.Integer(),
+      curve: Type.String(),
+      proving_system: Type.String(),
+      circuit_id: Type.String(),
+      vk_id: Type.String(),
+      public_inputs: Type.Array(Type.String()),
+      proof: Type.String(),
+      created_at: Type.String(),
+      prover: Type.String(),
+      signature: Type.Object({
+        algorithm: Type.String(),
+        public_key: Type.String(),
+        signature: Type.String(),
+      }),
+    }),
+    public_inputs: Type.Array(Type.String()),
+    coordinates: Type.Object({
+      a: g1Coordinates,
+      b: Type.Object({ x: fq2Coordinates, y: fq2Coordinates }),
+      c: g1Coordinates,
+    }),
+    verifying_key: Type.String(),
+    error_code: Type.Integer(),
+    error: Type.String(),
+  });
+
   const runDeployedServicesResponse = Type.Array(
     Type.Object({
-      answer: Type.Union([Type.String(), Type.Null()]),
+      answer: Type.Union([proofResponse, Type.Null()]),
       worker: Type.Object({
         host_id: Type.String(),
         pat_id: Type.String(),
@@ -114,10 +153,9 @@ export default async function (server) {
 
   server.post(
     "/my/callback/runDeployedServices",
-    { schema: { response: { 200: runDeployedServicesResponse } } },
+    { schema: { body: proofRequest, response: { 200: runDeployedServicesResponse } } },
     async (request, reply) => {
-      const { leaf_hex, sibling_hex } = request.body;
-      const result = await runDeployedServices(leaf_hex, sibling_hex);
+      const result = await runDeployedServices(request.body);
       return reply.send(result);
     },
   );
@@ -33,9 +33,13 @@ export type GetInfoParams = [config?: {ttl?: number}] | [peer: IFluenceClient$$,
 
 export type GetInfoResult = Promise<GetInfoResultType>;
 
-export type RunDeployedServicesParams = [leaf_hex: string, sibling_hex: string, config?: {ttl?: number}] | [peer: IFluenceClient$$, leaf_hex: string, sibling_hex: string, config?: {ttl?: number}];
+export type RunDeployedServicesParams = [request: { circuit_id: string; leaf: string; path_bits: boolean[]; root: string; siblings: string[]; }, config?: {ttl?: number}] | [peer: IFluenceClient$$, request: { circuit_id: string; leaf: string; path_bits: boolean[]; root: string; siblings: string[]; }, config?: {ttl?: number}];
 
-export type RunDeployedServicesResult = Promise<{ answer: string | null; worker: { host_id: string; pat_id: string; worker_id: string | null; }; }[]>;
+export type RunDeployedServicesResult = Promise<{ answer: { coordinates: { a: { x: string; y: string; }; b: { x: { c0: string; c1: string; }; y: { c0: string; c1: string; }; }; c: { x: string; y: string; }; }; envelope: { circuit_id: string; created_at: string; curve: string; proof: string; prover: string; proving_system: string; public_inputs: string[]; signature: { algorithm: string; public_key: string; signature: string; }; version: number; vk_id: string; }; error: string; error_code: number; public_inputs: string[]; verifying_key: string; } | null; worker: { host_id: string; pat_id: string; worker_id: string | null; }; }[]>;
+
+export type RunDeployedBatchParams = [request: { circuit_id: string; items: { leaf: string; path_bits: boolean[]; siblings: string[]; }[]; root: string; }, config?: {ttl?: number}] | [peer: IFluenceClient$$, request: { circuit_id: string; items: { leaf: string; path_bits: boolean[]; siblings: string[]; }[]; root: string; }, config?: {ttl?: number}];
+
+export type RunDeployedBatchResult = Promise<{ answer: { error: string; error_code: number; results: { coordinates: { a: { x: string; y: string; }; b: { x: { c0: string; c1: string; }; y: { c0: string; c1: string; }; }; c: { x: string; y: string; }; }; envelope: { circuit_id: string; created_at: string; curve: string; proof: string; prover: string; proving_system: string; public_inputs: string[]; signature: { algorithm: string; public_key: string; signature: string; }; version: number; vk_id: string; }; error: string; error_code: number; public_inputs: string[]; verifying_key: string; }[]; root: string; verifying_key: string; vk_id: string; } | null; worker: { host_id: string; pat_id: string; worker_id: string | null; }; }[]>;
 
 export type HelloWorldParams = [name: string, config?: {ttl?: number}] | [peer: IFluenceClient$$, name: string, config?: {ttl?: number}];
 
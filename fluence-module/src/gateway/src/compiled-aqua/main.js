@@ -568,11 +568,8 @@ export const runDeployedServices_script = `
          (seq
           (seq
            (seq
-            (seq
-             (call %init_peer_id% ("getDataSrv" "-relay-") [] -relay-)
-             (call %init_peer_id% ("getDataSrv" "leaf_hex") [] -leaf_hex-arg-)
-            )
-            (call %init_peer_id% ("getDataSrv" "sibling_hex") [] -sibling_hex-arg-)
+            (call %init_peer_id% ("getDataSrv" "-relay-") [] -relay-)
+            (call %init_peer_id% ("getDataSrv" "request") [] -request-arg-)
            )
            (new %Deals_obj_map
             (seq
@@ -661,7 +658,7 @@ export const runDeployedServices_script = `
                         )
                        )
                       )
-                      (call w-0.$.worker_id.[0] ("myService" "gen_proof") [-leaf_hex-arg- -sibling_hex-arg-] ret-0)
+                      (call w-0.$.worker_id.[0] ("myService" "gen_proof") [-request-arg-] ret-0)
                      )
                      (new $option-inline
                       (seq
@@ -764,13 +761,37 @@ export function runDeployedServices(...args) {
     "arrow": {
         "domain": {
             "fields": {
-                "leaf_hex": {
-                    "name": "string",
-                    "tag": "scalar"
-                },
-                "sibling_hex": {
-                    "name": "string",
-                    "tag": "scalar"
+                "request": {
+                    "name": "ProofRequest",
+                    "fields": {
+                        "circuit_id": {
+                            "name": "string",
+                            "tag": "scalar"
+                        },
+                        "leaf": {
+                            "name": "string",
+                            "tag": "scalar"
+                        },
+                        "path_bits": {
+                            "type": {
+                                "name": "bool",
+                                "tag": "scalar"
+                            },
+                            "tag": "array"
+                        },
+                        "root": {
+                            "name": "string",
+                            "tag": "scalar"
+                        },
+                        "siblings": {
+                            "type": {
+                                "name": "string",
+                                "tag": "scalar"
+                            },
+                            "tag": "array"
+                        }
+                    },
+                    "tag": "struct"
                 }
             },
             "tag": "labeledProduct"
@@ -783,8 +804,160 @@ export function runDeployedServices(...args) {
                         "fields": {
                             "answer": {
                                 "type": {
-                                    "name": "string",
-                                    "tag": "scalar"
+                                    "name": "ProofResponse",
+                                    "fields": {
+                                        "coordinates": {
+                                            "name": "ProofCoordinates",
+                                            "fields": {
+                                                "a": {
+                                                    "name": "G1Coordinates",
+                                                    "fields": {
+                                                        "x": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        },
+                                                        "y": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        }
+                                                    },
+                                                    "tag": "struct"
+                                                },
+                                                "b": {
+                                                    "name": "G2Coordinates",
+                                                    "fields": {
+                                                        "x": {
+                                                            "name": "Fq2Coordinates",
+                                                            "fields": {
+                                                                "c0": {
+                                                                    "name": "string",
+                                                                    "tag": "scalar"
+                                                                },
+                                                                "c1": {
+                                                                    "name": "string",
+                                                                    "tag": "scalar"
+                                                                }
+                                                            },
+                                                            "tag": "struct"
+                                                        },
+                                                        "y": {
+                                                            "name": "Fq2Coordinates",
+                                                            "fields": {
+                                                                "c0": {
+                                                                    "name": "string",
+                                                                    "tag": "scalar"
+                                                                },
+                                                                "c1": {
+                                                                    "name": "string",
+                                                                    "tag": "scalar"
+                                                                }
+                                                            },
+                                                            "tag": "struct"
+                                                        }
+                                                    },
+                                                    "tag": "struct"
+                                                },
+                                                "c": {
+                                                    "name": "G1Coordinates",
+                                                    "fields": {
+                                                        "x": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        },
+                                                        "y": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        }
+                                                    },
+                                                    "tag": "struct"
+                                                }
+                                            },
+                                            "tag": "struct"
+                                        },
+                                        "envelope": {
+                                            "name": "ProofEnvelope",
+                                            "fields": {
+                                                "circuit_id": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "created_at": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "curve": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "proof": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "prover": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "proving_system": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                },
+                                                "public_inputs": {
+                                                    "type": {
+                                                        "name": "string",
+                                                        "tag": "scalar"
+                                                    },
+                                                    "tag": "array"
+                                                },
+                                                "signature": {
+                                                    "name": "EnvelopeSignature",
+                                                    "fields": {
+                                                        "algorithm": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        },
+                                                        "public_key": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        },
+                                                        "signature": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        }
+                                                    },
+                                                    "tag": "struct"
+                                                },
+                                                "version": {
+                                                    "name": "u32",
+                                                    "tag": "scalar"
+                                                },
+                                                "vk_id": {
+                                                    "name": "string",
+                                                    "tag": "scalar"
+                                                }
+                                            },
+                                            "tag": "struct"
+                                        },
+                                        "error": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "error_code": {
+                                            "name": "u32",
+                                            "tag": "scalar"
+                                        },
+                                        "public_inputs": {
+                                            "type": {
+                                                "name": "string",
+                                                "tag": "scalar"
+                                            },
+                                            "tag": "array"
+                                        },
+                                        "verifying_key": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        }
+                                    },
+                                    "tag": "struct"
                                 },
                                 "tag": "option"
                             },
@@ -833,6 +1006,495 @@ export function runDeployedServices(...args) {
     );
 }
 
+export const runDeployedBatch_script = `
+(xor
+ (new $answers
+  (seq
+   (seq
+    (seq
+     (seq
+      (seq
+       (seq
+        (seq
+         (seq
+          (seq
+           (seq
+            (call %init_peer_id% ("getDataSrv" "-relay-") [] -relay-)
+            (call %init_peer_id% ("getDataSrv" "request") [] -request-arg-)
+           )
+           (new %Deals_obj_map
+            (seq
+             (ap ("myDeployment" []) %Deals_obj_map)
+             (canon %init_peer_id% %Deals_obj_map  Deals_obj)
+            )
+           )
+          )
+          (ap Deals_obj.$.myDeployment Deals_obj_flat)
+         )
+         (ap Deals_obj_flat.$.[0].dealIdOriginal Deals_obj_flat_flat)
+        )
+        (xor
+         (call -relay- ("subnet" "resolve") [Deals_obj_flat_flat] ret)
+         (fail :error:)
+        )
+       )
+       (new -if-error-
+        (xor
+         (match ret.$.success false
+          (seq
+           (new $array-inline
+            (seq
+             (seq
+              (ap "Failed to resolve subnet: " $array-inline)
+              (ap ret.$.error $array-inline)
+             )
+             (canon %init_peer_id% $array-inline  #array-inline-0)
+            )
+           )
+           (call %init_peer_id% ("run-console" "print") [#array-inline-0])
+          )
+         )
+         (seq
+          (ap :error: -if-error-)
+          (xor
+           (match :error:.$.error_code 10001
+            (null)
+           )
+           (fail -if-error-)
+          )
+         )
+        )
+       )
+      )
+      (fold ret.$.workers w-0
+       (seq
+        (new -if-else-error-
+         (new -else-error-
+          (new -if-error-
+           (xor
+            (match w-0.$.worker_id []
+             (seq
+              (new %BatchAnswer_obj_map
+               (seq
+                (seq
+                 (ap ("answer" []) %BatchAnswer_obj_map)
+                 (ap ("worker" w-0) %BatchAnswer_obj_map)
+                )
+                (canon %init_peer_id% %BatchAnswer_obj_map  BatchAnswer_obj)
+               )
+              )
+              (ap BatchAnswer_obj $answers)
+             )
+            )
+            (seq
+             (ap :error: -if-error-)
+             (xor
+              (match :error:.$.error_code 10001
+               (xor
+                (seq
+                 (seq
+                  (seq
+                   (seq
+                    (seq
+                     (seq
+                      (seq
+                       (new $-hop-
+                        (new #-hopc-
+                         (canon -relay- $-hop-  #-hopc-)
+                        )
+                       )
+                       (new $-hop-
+                        (new #-hopc-
+                         (canon w-0.$.host_id $-hop-  #-hopc-)
+                        )
+                       )
+                      )
+                      (call w-0.$.worker_id.[0] ("myService" "gen_proofs_batch") [-request-arg-] ret-0)
+                     )
+                     (new $option-inline
+                      (seq
+                       (xor
+                        (ap ret-0 $option-inline)
+                        (null)
+                       )
+                       (canon w-0.$.worker_id.[0] $option-inline  #option-inline-0)
+                      )
+                     )
+                    )
+                    (new %BatchAnswer_obj-0_map
+                     (seq
+                      (seq
+                       (ap ("answer" #option-inline-0) %BatchAnswer_obj-0_map)
+                       (ap ("worker" w-0) %BatchAnswer_obj-0_map)
+                      )
+                      (canon w-0.$.worker_id.[0] %BatchAnswer_obj-0_map  BatchAnswer_obj-0)
+                     )
+                    )
+                   )
+                   (ap BatchAnswer_obj-0 $answers)
+                  )
+                  (new $-hop-
+                   (new #-hopc-
+                    (canon w-0.$.host_id $-hop-  #-hopc-)
+                   )
+                  )
+                 )
+                 (new $-hop-
+                  (new #-hopc-
+                   (canon -relay- $-hop-  #-hopc-)
+                  )
+                 )
+                )
+                (seq
+                 (seq
+                  (seq
+                   (new $-hop-
+                    (new #-hopc-
+                     (canon w-0.$.host_id $-hop-  #-hopc-)
+                    )
+                   )
+                   (new $-hop-
+                    (new #-hopc-
+                     (canon -relay- $-hop-  #-hopc-)
+                    )
+                   )
+                  )
+                  (new $-hop-
+                   (new #-hopc-
+                    (canon %init_peer_id% $-hop-  #-hopc-)
+                   )
+                  )
+                 )
+                 (fail :error:)
+                )
+               )
+              )
+              (seq
+               (seq
+                (ap :error: -else-error-)
+                (xor
+                 (match :error:.$.error_code 10001
+                  (ap -if-error- -if-else-error-)
+                 )
+                 (ap -else-error- -if-else-error-)
+                )
+               )
+               (fail -if-else-error-)
+              )
+             )
+            )
+           )
+          )
+         )
+        )
+        (next w-0)
+       )
+       (null)
+      )
+     )
+     (canon %init_peer_id% $answers  #-answers-fix-0)
+    )
+    (ap #-answers-fix-0 -answers-flat-0)
+   )
+   (call %init_peer_id% ("callbackSrv" "response") [-answers-flat-0])
+  )
+ )
+ (call %init_peer_id% ("errorHandlingSrv" "error") [:error: 0])
+)
+`;
+
+
+export function runDeployedBatch(...args) {
+    return callFunction$$(
+        args,
+        {
+    "functionName": "runDeployedBatch",
+    "arrow": {
+        "domain": {
+            "fields": {
+                "request": {
+                    "name": "BatchProofRequest",
+                    "fields": {
+                        "circuit_id": {
+                            "name": "string",
+                            "tag": "scalar"
+                        },
+                        "items": {
+                            "type": {
+                                "name": "MembershipWitness",
+                                "fields": {
+                                    "leaf": {
+                                        "name": "string",
+                                        "tag": "scalar"
+                                    },
+                                    "path_bits": {
+                                        "type": {
+                                            "name": "bool",
+                                            "tag": "scalar"
+                                        },
+                                        "tag": "array"
+                                    },
+                                    "siblings": {
+                                        "type": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "tag": "array"
+                                    }
+                                },
+                                "tag": "struct"
+                            },
+                            "tag": "array"
+                        },
+                        "root": {
+                            "name": "string",
+                            "tag": "scalar"
+                        }
+                    },
+                    "tag": "struct"
+                }
+            },
+            "tag": "labeledProduct"
+        },
+        "codomain": {
+            "items": [
+                {
+                    "type": {
+                        "name": "BatchAnswer",
+                        "fields": {
+                            "answer": {
+                                "type": {
+                                    "name": "BatchProofResponse",
+                                    "fields": {
+                                        "error": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "error_code": {
+                                            "name": "u32",
+                                            "tag": "scalar"
+                                        },
+                                        "results": {
+                                            "type": {
+                                                "name": "ProofResponse",
+                                                "fields": {
+                                                    "coordinates": {
+                                                        "name": "ProofCoordinates",
+                                                        "fields": {
+                                                            "a": {
+                                                                "name": "G1Coordinates",
+                                                                "fields": {
+                                                                    "x": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    },
+                                                                    "y": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    }
+                                                                },
+                                                                "tag": "struct"
+                                                            },
+                                                            "b": {
+                                                                "name": "G2Coordinates",
+                                                                "fields": {
+                                                                    "x": {
+                                                                        "name": "Fq2Coordinates",
+                                                                        "fields": {
+                                                                            "c0": {
+                                                                                "name": "string",
+                                                                                "tag": "scalar"
+                                                                            },
+                                                                            "c1": {
+                                                                                "name": "string",
+                                                                                "tag": "scalar"
+                                                                            }
+                                                                        },
+                                                                        "tag": "struct"
+                                                                    },
+                                                                    "y": {
+                                                                        "name": "Fq2Coordinates",
+                                                                        "fields": {
+                                                                            "c0": {
+                                                                                "name": "string",
+                                                                                "tag": "scalar"
+                                                                            },
+                                                                            "c1": {
+                                                                                "name": "string",
+                                                                                "tag": "scalar"
+                                                                            }
+                                                                        },
+                                                                        "tag": "struct"
+                                                                    }
+                                                                },
+                                                                "tag": "struct"
+                                                            },
+                                                            "c": {
+                                                                "name": "G1Coordinates",
+                                                                "fields": {
+                                                                    "x": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    },
+                                                                    "y": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    }
+                                                                },
+                                                                "tag": "struct"
+                                                            }
+                                                        },
+                                                        "tag": "struct"
+                                                    },
+                                                    "envelope": {
+                                                        "name": "ProofEnvelope",
+                                                        "fields": {
+                                                            "circuit_id": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "created_at": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "curve": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "proof": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "prover": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "proving_system": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "public_inputs": {
+                                                                "type": {
+                                                                    "name": "string",
+                                                                    "tag": "scalar"
+                                                                },
+                                                                "tag": "array"
+                                                            },
+                                                            "signature": {
+                                                                "name": "EnvelopeSignature",
+                                                                "fields": {
+                                                                    "algorithm": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    },
+                                                                    "public_key": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    },
+                                                                    "signature": {
+                                                                        "name": "string",
+                                                                        "tag": "scalar"
+                                                                    }
+                                                                },
+                                                                "tag": "struct"
+                                                            },
+                                                            "version": {
+                                                                "name": "u32",
+                                                                "tag": "scalar"
+                                                            },
+                                                            "vk_id": {
+                                                                "name": "string",
+                                                                "tag": "scalar"
+                                                            }
+                                                        },
+                                                        "tag": "struct"
+                                                    },
+                                                    "error": {
+                                                        "name": "string",
+                                                        "tag": "scalar"
+                                                    },
+                                                    "error_code": {
+                                                        "name": "u32",
+                                                        "tag": "scalar"
+                                                    },
+                                                    "public_inputs": {
+                                                        "type": {
+                                                            "name": "string",
+                                                            "tag": "scalar"
+                                                        },
+                                                        "tag": "array"
+                                                    },
+                                                    "verifying_key": {
+                                                        "name": "string",
+                                                        "tag": "scalar"
+                                                    }
+                                                },
+                                                "tag": "struct"
+                                            },
+                                            "tag": "array"
+                                        },
+                                        "root": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "verifying_key": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "vk_id": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        }
+                                    },
+                                    "tag": "struct"
+                                },
+                                "tag": "option"
+                            },
+                            "worker": {
+                                "name": "Worker",
+                                "fields": {
+                                    "host_id": {
+                                        "name": "string",
+                                        "tag": "scalar"
+                                    },
+                                    "pat_id": {
+                                        "name": "string",
+                                        "tag": "scalar"
+                                    },
+                                    "worker_id": {
+                                        "type": {
+                                            "name": "string",
+                                            "tag": "scalar"
+                                        },
+                                        "tag": "option"
+                                    }
+                                },
+                                "tag": "struct"
+                            }
+                        },
+                        "tag": "struct"
+                    },
+                    "tag": "array"
+                }
+            ],
+            "tag": "unlabeledProduct"
+        },
+        "tag": "arrow"
+    },
+    "names": {
+        "relay": "-relay-",
+        "getDataSrv": "getDataSrv",
+        "callbackSrv": "callbackSrv",
+        "responseSrv": "callbackSrv",
+        "responseFnName": "response",
+        "errorHandlingSrv": "errorHandlingSrv",
+        "errorFnName": "error"
+    }
+},
+        runDeployedBatch_script
+    );
+}
+
 export const helloWorld_script = `
 (xor
  (seq
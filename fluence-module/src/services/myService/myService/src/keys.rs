@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::{thread_rng, RngCore};
+use ed25519_dalek::SigningKey;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument, warn};
+use zk_core::{vk_id, DataVerificationEntry, ZkError};
+
+use crate::{ServiceError, MEMBERSHIP_CIRCUIT_ID};
+
+/// Directory mapped into the module by `service.yaml`. Proving keys are
+/// stored there as `merkle_membership_depth<N>.pk`, uncompressed.
+pub const KEYS_DIR: &str = "/keys";
+
+/// Deepest Merkle path the service proves, about a million leaves. Every
+/// depth proved gets its own cached key, so requests cannot make the worker
+/// run setups for arbitrary depths.
+pub const MAX_DEPTH: usize = 20;
+
+/// Directory mapped into the module for the worker's own signing key, kept
+/// apart from `KEYS_DIR` so that it is never packaged with the proving keys.
+pub const ATTESTATION_DIR: &str = "/attestation";
+
+/// Raw 32-byte seed of the Ed25519 key this worker signs its proof envelopes
+/// with, in `ATTESTATION_DIR`. It is created on first use and must not be
+/// shipped with the service: every worker needs its own.
+pub const ATTESTATION_KEY_FILE: &str = "attestation_ed25519.key";
+
+/// A proving key for one tree depth, kept for the module's lifetime.
+pub struct KeyEntry {
+    pub pk: ProvingKey<Bn254>,
+    pub vk_id: String,
+    pub fingerprint: String,
+    /// File the key was read from, `None` if it was generated in-process
+    /// because no file exists for this depth.
+    pub path: Option<PathBuf>,
+}
+
+thread_local! {
+    /// Verifying keys known to this module instance, by key id.
+    static VERIFYING_KEYS: RefCell<HashMap<String, VerifyingKey<Bn254>>> =
+        RefCell::new(HashMap::new());
+
+    /// Proving keys by tree depth, loaded on first use.
+    static PROVING_KEYS: RefCell<HashMap<usize, Rc<KeyEntry>>> = RefCell::new(HashMap::new());
+
+    static ATTESTATION_KEY: RefCell<Option<Rc<SigningKey>>> = const { RefCell::new(None) };
+}
+
+/// Remembers `vk` so later `verify_proof` calls can refer to it by id.
+pub fn register(vk: &VerifyingKey<Bn254>) -> Result<String, ZkError> {
+    let id = vk_id(vk)?;
+    VERIFYING_KEYS.with(|keys| keys.borrow_mut().insert(id.clone(), vk.clone()));
+    Ok(id)
+}
+
+pub fn lookup(id: &str) -> Option<VerifyingKey<Bn254>> {
+    VERIFYING_KEYS.with(|keys| keys.borrow().get(id).cloned())
+}
+
+pub fn key_path(depth: usize) -> PathBuf {
+    Path::new(KEYS_DIR).join(format!("{}_depth{}.pk", MEMBERSHIP_CIRCUIT_ID, depth))
+}
+
+/// Depths for which a key file is present in `KEYS_DIR`.
+pub fn stored_depths() -> Vec<usize> {
+    let prefix = format!("{}_depth", MEMBERSHIP_CIRCUIT_ID);
+    let mut depths: Vec<usize> = fs::read_dir(KEYS_DIR)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            name.strip_prefix(&prefix)?
+                .strip_suffix(".pk")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    depths.sort_unstable();
+    depths
+}
+
+/// Depths of all keys loaded or generated so far.
+pub fn cached_depths() -> Vec<usize> {
+    let mut depths: Vec<usize> = PROVING_KEYS.with(|keys| keys.borrow().keys().copied().collect());
+    depths.sort_unstable();
+    depths
+}
+
+/// Returns the proving key for `depth`, reading it from `KEYS_DIR` on first
+/// use. Without a key file a fresh setup is run once and its key cached, so
+/// proofs from such a worker only verify against the key it reports.
+#[instrument(name = "setup")]
+pub fn proving_key(depth: usize) -> Result<Rc<KeyEntry>, ServiceError> {
+    if let Some(entry) = PROVING_KEYS.with(|keys| keys.borrow().get(&depth).cloned()) {
+        return Ok(entry);
+    }
+    if depth > MAX_DEPTH {
+        return Err(ServiceError::DepthTooLarge(depth));
+    }
+
+    let shape = CircuitShape::of(depth)?;
+    let path = key_path(depth);
+    let (pk, path) = match fs::read(&path) {
+        Ok(bytes) => {
+            debug!(path = %path.display(), bytes = bytes.len(), "loading proving key");
+            // The key comes from the operator's own storage, so the costly
+            // subgroup checks are skipped; its shape is still checked below.
+            let pk = ProvingKey::<Bn254>::deserialize_uncompressed_unchecked(&bytes[..])?;
+            if !shape.matches(&pk) {
+                return Err(ServiceError::KeyShape(depth));
+            }
+            (pk, Some(path))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            info!(path = %path.display(), "no proving key file, running setup");
+            let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+                DataVerificationEntry::blank(depth)?,
+                &mut thread_rng(),
+            )?;
+            (pk, None)
+        }
+        Err(e) => return Err(ServiceError::KeyIo(path, e)),
+    };
+
+    let entry = Rc::new(KeyEntry {
+        vk_id: register(&pk.vk)?,
+        pk,
+        fingerprint: shape.fingerprint,
+        path,
+    });
+    PROVING_KEYS.with(|keys| keys.borrow_mut().insert(depth, entry.clone()));
+    Ok(entry)
+}
+
+/// Returns this worker's signing key, reading it from `ATTESTATION_DIR` or creating
+/// it there on first use. If the key cannot be stored it is kept in memory
+/// only, so the worker's identity changes on restart.
+pub fn attestation_key() -> Result<Rc<SigningKey>, ServiceError> {
+    if let Some(key) = ATTESTATION_KEY.with(|key| key.borrow().clone()) {
+        return Ok(key);
+    }
+
+    let path = Path::new(ATTESTATION_DIR).join(ATTESTATION_KEY_FILE);
+    let seed = match fs::read(&path) {
+        Ok(bytes) => <[u8; 32]>::try_from(&bytes[..]).map_err(|_| {
+            let e = io::Error::new(io::ErrorKind::InvalidData, "expected a 32-byte seed");
+            ServiceError::KeyIo(path.clone(), e)
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut seed = [0u8; 32];
+            thread_rng().fill_bytes(&mut seed);
+            match fs::write(&path, seed) {
+                Ok(()) => info!(path = %path.display(), "created attestation key"),
+                Err(e) => warn!(path = %path.display(), error = %e, "attestation key not stored"),
+            }
+            seed
+        }
+        Err(e) => return Err(ServiceError::KeyIo(path, e)),
+    };
+
+    let key = Rc::new(SigningKey::from_bytes(&seed));
+    ATTESTATION_KEY.with(|cached| *cached.borrow_mut() = Some(key.clone()));
+    Ok(key)
+}
+
+/// Size and fingerprint of the membership circuit's constraint system.
+struct CircuitShape {
+    num_instance_variables: usize,
+    num_witness_variables: usize,
+    /// Hex SHA-256 over the circuit id, the variable counts and the R1CS
+    /// matrices, as synthesized for setup.
+    fingerprint: String,
+}
+
+impl CircuitShape {
+    fn of(depth: usize) -> Result<Self, ServiceError> {
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Setup);
+        DataVerificationEntry::blank(depth)?.generate_constraints(cs.clone())?;
+        cs.finalize();
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(MEMBERSHIP_CIRCUIT_ID.as_bytes());
+        for count in [
+            matrices.num_instance_variables,
+            matrices.num_witness_variables,
+            matrices.num_constraints,
+        ] {
+            hasher.update((count as u64).to_le_bytes());
+        }
+        for matrix in [&matrices.a, &matrices.b, &matrices.c] {
+            for row in matrix {
+                hasher.update((row.len() as u64).to_le_bytes());
+                for (coeff, index) in row {
+                    hasher.update(coeff.into_bigint().to_bytes_le());
+                    hasher.update((*index as u64).to_le_bytes());
+                }
+            }
+        }
+
+        Ok(CircuitShape {
+            num_instance_variables: matrices.num_instance_variables,
+            num_witness_variables: matrices.num_witness_variables,
+            fingerprint: hex::encode(hasher.finalize()),
+        })
+    }
+
+    fn matches(&self, pk: &ProvingKey<Bn254>) -> bool {
+        pk.vk.gamma_abc_g1.len() == self.num_instance_variables
+            && pk.a_query.len() == self.num_instance_variables + self.num_witness_variables
+    }
+}
@@ -1,260 +1,655 @@
+use std::convert::TryFrom;
+
 use marine_rs_sdk::marine;
 use marine_rs_sdk::module_manifest;
+use marine_rs_sdk::WasmLoggerBuilder;
 
-use ark_bn254::{Bn254, Fq as ArkFq, Fr as ArkFr, G1Affine, G2Affine};
-use ark_ec::pairing::Pairing;
-use ark_ff::{BigInteger, Field, PrimeField};
-use ark_groth16::{Groth16, Proof};
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_groth16::{prepare_verifying_key, Groth16, Proof, VerifyingKey};
 
-use ark_r1cs_std::fields::fp::FpVar;
-use ark_r1cs_std::prelude::*;
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::rand::thread_rng;
-use chrono::Utc;
-use hex;
-use light_poseidon::{Poseidon, PoseidonError, PoseidonHasher};
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use serde_json::json;
-use std::error::Error;
-use std::fmt;
+use thiserror::Error;
+use tracing::instrument;
+use zk_core::attestation::ED25519;
+use zk_core::{
+    compute_root, fr_to_hex, parse_data, parse_fr, sign_envelope, verify_merkle_tree,
+    verify_signature, vk_id, DataVerificationEntry, MerkleTreeData, ZkError, MEMBERSHIP_CIRCUIT_ID,
+};
+
+mod keys;
+mod metrics;
+mod records;
+
+use keys::KeyEntry;
+use records::{
+    AttestationKey, BatchProofRequest, BatchProofResponse, Fq2Coordinates, G1Coordinates,
+    G2Coordinates, KeyInfo, ProofCoordinates, ProofEnvelope, ProofRequest, ProofResponse,
+    ProvingMetrics, Verdict, VerifyRequest,
+};
 
 module_manifest!();
 
-fn main() {}
+/// Tracing events are forwarded to the Marine logger, filtered by the
+/// `WASM_LOG` environment variable (e.g. `WASM_LOG=myService=debug`).
+pub fn main() {
+    // Fails only if a logger is already installed, which then keeps logging.
+    let _ = WasmLoggerBuilder::new().build();
+}
 
-#[derive(Debug)]
-pub struct MySynthesisError(pub SynthesisError);
+/// Failures of the service endpoints, each reported to Aqua with a stable
+/// `code`. Codes of `Core` errors are those of `ZkError`.
+#[derive(Debug, Error)]
+enum ServiceError {
+    #[error(transparent)]
+    Core(#[from] ZkError),
+    #[error("unknown circuit id: {0}")]
+    UnknownCircuit(String),
+    #[error("the path does not hash to the requested root")]
+    RootMismatch,
+    #[error("unknown verifying key id: {0}")]
+    UnknownKey(String),
+    #[error("verifying key does not match the envelope's vk_id")]
+    KeyMismatch,
+    #[error("cannot read {}: {1}", .0.display())]
+    KeyIo(std::path::PathBuf, std::io::Error),
+    #[error("proving key does not fit the membership circuit at depth {0}")]
+    KeyShape(usize),
+    #[error("path of depth {0} in a batch of depth {1}")]
+    DepthMismatch(usize, usize),
+    #[error("path of depth {0} is deeper than the supported {max}", max = keys::MAX_DEPTH)]
+    DepthTooLarge(usize),
+}
 
-impl fmt::Display for MySynthesisError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+impl ServiceError {
+    fn code(&self) -> u32 {
+        match self {
+            ServiceError::Core(e) => e.code(),
+            ServiceError::UnknownCircuit(_) => 3,
+            ServiceError::RootMismatch => 4,
+            ServiceError::UnknownKey(_) => 8,
+            ServiceError::KeyMismatch => 9,
+            ServiceError::KeyIo(..) => 10,
+            ServiceError::KeyShape(_) => 11,
+            ServiceError::DepthMismatch(..) => 12,
+            ServiceError::DepthTooLarge(_) => 20,
+        }
     }
 }
 
-impl Error for MySynthesisError {}
-
-#[derive(Debug)]
-struct MyPoseidonError(PoseidonError);
-
-impl From<PoseidonError> for MyPoseidonError {
-    fn from(e: PoseidonError) -> Self {
-        MyPoseidonError(e)
+impl From<SynthesisError> for ServiceError {
+    fn from(e: SynthesisError) -> Self {
+        ServiceError::Core(e.into())
     }
 }
 
-impl From<MyPoseidonError> for SynthesisError {
-    fn from(e: MyPoseidonError) -> Self {
-        SynthesisError::AssignmentMissing // Or use a more appropriate SynthesisError variant
+impl From<SerializationError> for ServiceError {
+    fn from(e: SerializationError) -> Self {
+        ServiceError::Core(e.into())
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct MyFqWrapper(pub ArkFq);
-
-impl Serialize for MyFqWrapper {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut bytes = Vec::new();
-        self.0
-            .serialize_uncompressed(&mut bytes)
-            .map_err(serde::ser::Error::custom)?;
-        serializer.serialize_bytes(&bytes)
-    }
+#[instrument(name = "proving", skip_all, fields(depth = data.siblings.len()))]
+fn generate_proof(
+    data: &MerkleTreeData,
+    root: ArkFr,
+    key: &KeyEntry,
+) -> Result<Proof<Bn254>, ServiceError> {
+    let mut csprng = thread_rng();
+
+    let circuit = DataVerificationEntry::new(data, root)?;
+
+    let proof =
+        Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &key.pk, &mut csprng)?;
+
+    Ok(proof)
 }
 
-impl<'de> Deserialize<'de> for MyFqWrapper {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let bytes: &[u8] = Deserialize::deserialize(deserializer)?;
-        let fq = ArkFq::deserialize_uncompressed(bytes).map_err(serde::de::Error::custom)?;
-        Ok(MyFqWrapper(fq))
+fn get_proof_coordinates(proof: &Proof<Bn254>) -> ProofCoordinates {
+    ProofCoordinates {
+        a: G1Coordinates {
+            x: proof.a.x.to_string(),
+            y: proof.a.y.to_string(),
+        },
+        b: G2Coordinates {
+            x: Fq2Coordinates {
+                c0: proof.b.x.c0.to_string(),
+                c1: proof.b.x.c1.to_string(),
+            },
+            y: Fq2Coordinates {
+                c0: proof.b.y.c0.to_string(),
+                c1: proof.b.y.c1.to_string(),
+            },
+        },
+        c: G1Coordinates {
+            x: proof.c.x.to_string(),
+            y: proof.c.y.to_string(),
+        },
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct MerkleTreeData {
-    leaf: ArkFr,
-    siblings: Vec<ArkFr>,
-    path_bits: Vec<bool>,
+/// Proves that `request.leaf` is included under `request.root`.
+///
+/// Never traps: failures come back as a `ProofResponse` with a non-zero
+/// `error_code`.
+#[marine]
+pub fn gen_proof(request: ProofRequest) -> ProofResponse {
+    match try_gen_proof(&request) {
+        Ok(response) => response,
+        Err(e) => ProofResponse::failure(e.code(), e.to_string()),
+    }
 }
 
-fn verify_merkle_tree(data: &MerkleTreeData) -> Result<bool, Box<dyn Error>> {
-    let mut current_hash = data.leaf;
+fn try_gen_proof(request: &ProofRequest) -> Result<ProofResponse, ServiceError> {
+    let circuit_id = resolve_circuit(&request.circuit_id)?;
+    let data = parse_path(&request.leaf, &request.siblings, &request.path_bits)?;
+    let root = if request.root.is_empty() {
+        compute_root(&data)?
+    } else {
+        parse_fr("root", &request.root)?
+    };
 
-    for (sibling_hash, path_bit) in data.siblings.iter().zip(data.path_bits.iter()) {
-        let mut hasher = Poseidon::<ArkFr>::new_circom(2)?;
-        let (left, right) = if *path_bit {
-            (current_hash, *sibling_hash)
-        } else {
-            (*sibling_hash, current_hash)
-        };
+    let key = keys::proving_key(data.siblings.len())?;
+    let mut response = prove_membership(circuit_id, &data, root, &key)?;
+    response.verifying_key = verifying_key_hex(&key)?;
+    Ok(response)
+}
 
-        current_hash = hasher.hash(&[left, right])?;
+/// Measures a full setup, prove and verify run for `request` on this worker,
+/// together with the size of the circuit at the request's depth.
+#[marine]
+pub fn proving_metrics(request: ProofRequest) -> ProvingMetrics {
+    match try_proving_metrics(&request) {
+        Ok(metrics) => metrics,
+        Err(e) => ProvingMetrics::failure(e.code(), e.to_string()),
     }
+}
 
-    Ok(true)
+fn try_proving_metrics(request: &ProofRequest) -> Result<ProvingMetrics, ServiceError> {
+    resolve_circuit(&request.circuit_id)?;
+    let data = parse_path(&request.leaf, &request.siblings, &request.path_bits)?;
+    let root = if request.root.is_empty() {
+        compute_root(&data)?
+    } else {
+        parse_fr("root", &request.root)?
+    };
+    metrics::measure(&data, root)
 }
 
-fn parse_data(leaf_hex: &str, sibling_hex: &str) -> Result<MerkleTreeData, Box<dyn Error>> {
-    let leaf_bytes = hex::decode(leaf_hex)?;
-    let leaf = ArkFr::from_le_bytes_mod_order(&leaf_bytes);
+/// Proves many leaves under the same root with one proving key.
+///
+/// Failures of single items are reported in their own `ProofResponse`; only
+/// problems affecting the whole batch (unknown circuit, bad root, key loading)
+/// fail the batch.
+#[marine]
+pub fn gen_proofs_batch(request: BatchProofRequest) -> BatchProofResponse {
+    match try_gen_proofs_batch(&request) {
+        Ok(response) => response,
+        Err(e) => BatchProofResponse::failure(e.code(), e.to_string()),
+    }
+}
 
-    let sibling_bytes = hex::decode(sibling_hex)?;
-    let sibling = ArkFr::from_le_bytes_mod_order(&sibling_bytes);
+fn try_gen_proofs_batch(request: &BatchProofRequest) -> Result<BatchProofResponse, ServiceError> {
+    let circuit_id = resolve_circuit(&request.circuit_id)?;
+    let items: Vec<Result<MerkleTreeData, ServiceError>> = request
+        .items
+        .iter()
+        .map(|item| parse_path(&item.leaf, &item.siblings, &item.path_bits))
+        .collect();
+
+    // The batch depth and, when not given, the root come from the first
+    // well-formed item.
+    let first = match items.iter().find_map(|item| item.as_ref().ok()) {
+        Some(first) => first,
+        None => {
+            return Ok(BatchProofResponse {
+                root: request.root.clone(),
+                results: items.into_iter().map(failed_item).collect(),
+                ..Default::default()
+            })
+        }
+    };
+    let depth = first.siblings.len();
+    let root = if request.root.is_empty() {
+        compute_root(first)?
+    } else {
+        parse_fr("root", &request.root)?
+    };
+    let key = keys::proving_key(depth)?;
+
+    let results = items
+        .into_iter()
+        .map(|item| {
+            item.and_then(|data| {
+                if data.siblings.len() != depth {
+                    return Err(ServiceError::DepthMismatch(data.siblings.len(), depth));
+                }
+                prove_membership(circuit_id, &data, root, &key)
+            })
+        })
+        .map(|result| result.unwrap_or_else(|e| ProofResponse::failure(e.code(), e.to_string())))
+        .collect();
+
+    Ok(BatchProofResponse {
+        root: fr_to_hex(&root),
+        vk_id: key.vk_id.clone(),
+        verifying_key: verifying_key_hex(&key)?,
+        results,
+        error_code: 0,
+        error: String::new(),
+    })
+}
 
-    let path_bits = vec![false];
+fn failed_item(item: Result<MerkleTreeData, ServiceError>) -> ProofResponse {
+    match item {
+        Ok(_) => ProofResponse::default(),
+        Err(e) => ProofResponse::failure(e.code(), e.to_string()),
+    }
+}
 
-    let data = MerkleTreeData {
-        leaf,
-        siblings: vec![sibling],
-        path_bits,
-    };
+/// Parses a membership path, rejecting paths deeper than `keys::MAX_DEPTH`
+/// before a key is set up for them.
+fn parse_path(
+    leaf: &str,
+    siblings: &[String],
+    path_bits: &[bool],
+) -> Result<MerkleTreeData, ServiceError> {
+    if siblings.len() > keys::MAX_DEPTH {
+        return Err(ServiceError::DepthTooLarge(siblings.len()));
+    }
+    Ok(parse_data(leaf, siblings, path_bits)?)
+}
 
-    Ok(data)
+fn resolve_circuit(circuit_id: &str) -> Result<&'static str, ServiceError> {
+    match circuit_id {
+        "" | MEMBERSHIP_CIRCUIT_ID => Ok(MEMBERSHIP_CIRCUIT_ID),
+        other => Err(ServiceError::UnknownCircuit(other.to_string())),
+    }
 }
 
-#[derive(Clone)]
-struct DataVerificationEntry {
-    leaf: ArkFr,
-    siblings: Vec<ArkFr>,
-    path_bits: Vec<bool>,
+/// Proves one leaf under `root`. The response leaves `verifying_key` empty;
+/// callers attach it once per request.
+fn prove_membership(
+    circuit_id: &str,
+    data: &MerkleTreeData,
+    root: ArkFr,
+    key: &KeyEntry,
+) -> Result<ProofResponse, ServiceError> {
+    if !verify_merkle_tree(data, &root)? {
+        return Err(ServiceError::RootMismatch);
+    }
+    let proof = generate_proof(data, root, key)?;
+    let prover = marine_rs_sdk::get_call_parameters().worker_id;
+    let mut envelope =
+        zk_core::ProofEnvelope::new(circuit_id, &key.pk.vk, &proof, &[root], &prover)?;
+    let signing_key = keys::attestation_key()?;
+    sign_envelope(&mut envelope, &signing_key)?;
+
+    Ok(ProofResponse {
+        envelope: ProofEnvelope::from(envelope),
+        public_inputs: vec![fr_to_hex(&root)],
+        coordinates: get_proof_coordinates(&proof),
+        verifying_key: String::new(),
+        error_code: 0,
+        error: String::new(),
+    })
 }
 
-impl ConstraintSynthesizer<ArkFr> for DataVerificationEntry {
-    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
-        println!("Starting generate_constraints");
+fn verifying_key_hex(key: &KeyEntry) -> Result<String, SerializationError> {
+    let mut vk_bytes = vec![];
+    key.pk.vk.serialize_uncompressed(&mut vk_bytes)?;
+    Ok(hex::encode(vk_bytes))
+}
 
-        let leaf_var = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(self.leaf))?;
-        let mut current_hash = leaf_var.clone();
+/// Checks a proof produced by any worker, e.g. to cross-verify the answers
+/// of a `gen_proof` fan-out before accepting one.
+#[marine]
+pub fn verify_proof(request: VerifyRequest) -> Verdict {
+    match try_verify_proof(&request) {
+        Ok(verdict) => verdict,
+        Err(e) => Verdict::failure(e.code(), e.to_string()),
+    }
+}
 
-        for (i, (sibling_hash, path_bit)) in
-            self.siblings.iter().zip(self.path_bits.iter()).enumerate()
-        {
-            println!("Processing sibling {} with path_bit {}", i, path_bit);
+#[instrument(name = "verification", skip_all)]
+fn try_verify_proof(request: &VerifyRequest) -> Result<Verdict, ServiceError> {
+    let vk = resolve_verifying_key(request)?;
+    let vk_id = vk_id(&vk)?;
+    if !request.envelope.vk_id.is_empty() && request.envelope.vk_id != vk_id {
+        return Err(ServiceError::KeyMismatch);
+    }
 
-            let sibling_var = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(*sibling_hash))?;
+    let envelope = zk_core::ProofEnvelope::try_from(&request.envelope)?;
+    let signer = match envelope.signature {
+        Some(_) => hex::encode(verify_signature(&envelope)?.to_bytes()),
+        None => String::new(),
+    };
+    let proof = envelope.decode_proof()?;
+    let public_inputs = if request.public_inputs.is_empty() {
+        envelope.decode_public_inputs()?
+    } else {
+        request
+            .public_inputs
+            .iter()
+            .map(|input| parse_fr("public_inputs", input))
+            .collect::<Result<Vec<_>, _>>()?
+    };
 
-            let (left, right) = if *path_bit {
-                (current_hash.clone(), sibling_var)
-            } else {
-                (sibling_var, current_hash.clone())
-            };
-            println!("left: {:?}", left);
-            println!("right: {:?}", right);
-
-            let mut hasher = Poseidon::<ArkFr>::new_circom(2).map_err(|e| {
-                println!("Error creating Poseidon hasher: {:?}", e);
-                MyPoseidonError::from(e)
-            })?;
-            let result_hash_var = FpVar::<ArkFr>::new_witness(cs.clone(), || {
-                let result_hash = hasher.hash(&[self.leaf, *sibling_hash]).map_err(|e| {
-                    println!("Error hashing values: {:?}", e);
-                    MyPoseidonError::from(e)
-                })?;
-                Ok(result_hash)
-            })?;
-            current_hash = result_hash_var;
-        }
+    // A wrong number of public inputs is a proof that does not verify, not a
+    // malformed request.
+    let valid = public_inputs.len() + 1 == vk.gamma_abc_g1.len()
+        && Groth16::<Bn254>::verify_proof(&prepare_verifying_key(&vk), &proof, &public_inputs)
+            .map_err(ZkError::Verification)?;
+
+    Ok(Verdict {
+        valid,
+        vk_id,
+        signer,
+        error_code: 0,
+        error: String::new(),
+    })
+}
 
-        let root_hash_var = FpVar::<ArkFr>::new_input(cs.clone(), || {
-            let fr_element: ArkFr = ArkFr::from_le_bytes_mod_order(&[
-                0xba, 0x9d, 0x33, 0x2d, 0xbc, 0xc4, 0xac, 0xeb, 0x13, 0x1b, 0xf3, 0x1f, 0x95, 0xb1,
-                0x63, 0xf6, 0x32, 0x43, 0xf9, 0x94, 0xf4, 0x23, 0x23, 0x1c, 0x57, 0x78, 0x3c, 0x2c,
-                0x5b, 0xaa, 0xb6, 0x07,
-            ]);
-            println!("root_hash_var created: {:?}", fr_element);
-            Ok(fr_element)
+fn resolve_verifying_key(request: &VerifyRequest) -> Result<VerifyingKey<Bn254>, ServiceError> {
+    if !request.verifying_key.is_empty() {
+        let bytes = hex::decode(&request.verifying_key).map_err(|source| ZkError::InvalidHex {
+            field: "verifying_key",
+            source,
         })?;
+        return Ok(VerifyingKey::deserialize_uncompressed(&bytes[..])?);
+    }
 
-        // root_hash_var.enforce_equal(&current_hash)?;
+    let id = if request.key_id.is_empty() {
+        &request.envelope.vk_id
+    } else {
+        &request.key_id
+    };
+    if let Some(vk) = keys::lookup(id) {
+        return Ok(vk);
+    }
 
-        Ok(())
+    // Keys are registered as they are loaded, so a worker that has not proved
+    // anything yet knows none of its stored keys.
+    for depth in keys::stored_depths() {
+        if keys::proving_key(depth).is_ok_and(|key| key.vk_id == *id) {
+            break;
+        }
     }
+    keys::lookup(id).ok_or_else(|| ServiceError::UnknownKey(id.to_string()))
 }
 
-fn serialize_proof<E: Pairing>(proof: &Proof<E>) -> Result<String, Box<dyn Error>> {
-    let mut proof_bytes = vec![];
-    proof.serialize_uncompressed(&mut proof_bytes)?;
-    Ok(hex::encode(proof_bytes))
+/// Public key this worker signs its proof envelopes with, so that callers
+/// can pin the workers whose answers they accept.
+#[marine]
+pub fn attestation_key() -> AttestationKey {
+    match keys::attestation_key() {
+        Ok(key) => AttestationKey {
+            algorithm: ED25519.to_string(),
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            ..Default::default()
+        },
+        Err(e) => AttestationKey {
+            error_code: e.code(),
+            error: e.to_string(),
+            ..Default::default()
+        },
+    }
 }
 
-fn deserialize_proof(proof_hex: &str) -> Result<Proof<Bn254>, Box<dyn Error>> {
-    let proof_bytes = hex::decode(proof_hex)?;
-    let mut proof_reader = &proof_bytes[..];
-
-    let a = G1Affine::deserialize_uncompressed(&mut proof_reader)?;
-    let b = G2Affine::deserialize_uncompressed(&mut proof_reader)?;
-    let c = G1Affine::deserialize_uncompressed(&mut proof_reader)?;
-
-    Ok(Proof { a, b, c })
+/// Describes the proving keys of this worker: every key file in the mapped
+/// keys directory plus any key generated in-process. Key files are loaded
+/// (and cached) by this call.
+#[marine]
+pub fn key_info() -> Vec<KeyInfo> {
+    let mut depths = keys::stored_depths();
+    depths.extend(keys::cached_depths());
+    depths.sort_unstable();
+    depths.dedup();
+
+    depths
+        .into_iter()
+        .map(|depth| match keys::proving_key(depth) {
+            Ok(key) => KeyInfo {
+                circuit_id: MEMBERSHIP_CIRCUIT_ID.to_string(),
+                depth: depth as u32,
+                circuit_fingerprint: key.fingerprint.clone(),
+                vk_id: key.vk_id.clone(),
+                path: key
+                    .path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            Err(e) => KeyInfo {
+                circuit_id: MEMBERSHIP_CIRCUIT_ID.to_string(),
+                depth: depth as u32,
+                error_code: e.code(),
+                error: e.to_string(),
+                ..Default::default()
+            },
+        })
+        .collect()
 }
 
-fn generate_proof(data: &MerkleTreeData) -> Result<String, Box<dyn Error>> {
-    let mut csprng = thread_rng();
+// These tests run against the compiled Wasm module, so `fluence build` has to
+// be run first to produce it and its service config.
+#[cfg(test)]
+mod tests {
+    use marine_rs_sdk_test::marine_test;
+
+    const LEAF: &str = "280b47d864db9cd075eea86160434843b1e30262a6b6cea1f67762af82c9b62e";
+    const SIBLING: &str = "0b380602cc91a0f4d03949bb899c766cc3cb7967fd323e5df3f3726fe9eee905";
+
+    #[marine_test(config_path = "../../../../.fluence/service-configs/myService.toml")]
+    fn test_gen_proof_verifies(service: marine_test_env::myService::ModuleInterface) {
+        use marine_test_env::myService::{ProofRequest, VerifyRequest};
+
+        let response = service.gen_proof(ProofRequest {
+            leaf: LEAF.to_string(),
+            siblings: vec![SIBLING.to_string()],
+            path_bits: vec![false],
+            root: String::new(),
+            circuit_id: String::new(),
+        });
+        assert_eq!(response.error_code, 0, "{}", response.error);
+        assert_eq!(response.envelope.circuit_id, "merkle_membership");
+        assert_eq!(response.envelope.version, 1);
+        assert_eq!(response.envelope.proving_system, "groth16");
+        assert_eq!(response.envelope.public_inputs, response.public_inputs);
+        assert_eq!(response.public_inputs.len(), 1);
+
+        // The root the worker computed is accepted when passed explicitly.
+        let again = service.gen_proof(ProofRequest {
+            leaf: LEAF.to_string(),
+            siblings: vec![SIBLING.to_string()],
+            path_bits: vec![false],
+            root: response.public_inputs[0].clone(),
+            circuit_id: "merkle_membership".to_string(),
+        });
+        assert_eq!(again.error_code, 0, "{}", again.error);
+        assert_eq!(again.envelope.vk_id, response.envelope.vk_id);
+
+        let verdict = service.verify_proof(VerifyRequest {
+            envelope: response.envelope.clone(),
+            public_inputs: response.public_inputs.clone(),
+            verifying_key: response.verifying_key.clone(),
+            key_id: String::new(),
+        });
+        assert_eq!(verdict.error_code, 0, "{}", verdict.error);
+        assert!(verdict.valid);
+        assert_eq!(verdict.vk_id, response.envelope.vk_id);
+
+        // Signed by this worker's attestation key.
+        let key = service.attestation_key();
+        assert_eq!(key.error_code, 0, "{}", key.error);
+        assert_eq!(response.envelope.signature.algorithm, "ed25519");
+        assert_eq!(verdict.signer, key.public_key);
+
+        // Looked up by the envelope's vk_id among the worker's own keys, with
+        // the envelope's own public inputs.
+        let verdict = service.verify_proof(VerifyRequest {
+            envelope: response.envelope.clone(),
+            public_inputs: vec![],
+            verifying_key: String::new(),
+            key_id: String::new(),
+        });
+        assert!(verdict.valid);
+
+        let mut future = response.envelope.clone();
+        future.version = 2;
+        let verdict = service.verify_proof(VerifyRequest {
+            envelope: future,
+            public_inputs: vec![],
+            verifying_key: String::new(),
+            key_id: String::new(),
+        });
+        assert_eq!(verdict.error_code, 16);
+
+        let mut altered = response.envelope.clone();
+        altered.prover = "another-worker".to_string();
+        let verdict = service.verify_proof(VerifyRequest {
+            envelope: altered,
+            public_inputs: vec![],
+            verifying_key: String::new(),
+            key_id: String::new(),
+        });
+        assert_eq!(verdict.error_code, 18);
+
+        let verdict = service.verify_proof(VerifyRequest {
+            envelope: response.envelope,
+            public_inputs: vec![SIBLING.to_string()],
+            verifying_key: response.verifying_key,
+            key_id: String::new(),
+        });
+        assert_eq!(verdict.error_code, 0, "{}", verdict.error);
+        assert!(!verdict.valid);
+    }
 
-    let circuit = DataVerificationEntry {
-        leaf: data.leaf,
-        siblings: data.siblings.clone(),
-        path_bits: data.path_bits.clone(),
-    };
+    #[marine_test(config_path = "../../../../.fluence/service-configs/myService.toml")]
+    fn test_gen_proof_errors(service: marine_test_env::myService::ModuleInterface) {
+        use marine_test_env::myService::ProofRequest;
+
+        let request =
+            |leaf: &str, siblings: Vec<&str>, path_bits: Vec<bool>, root: &str| ProofRequest {
+                leaf: leaf.to_string(),
+                siblings: siblings.into_iter().map(String::from).collect(),
+                path_bits,
+                root: root.to_string(),
+                circuit_id: String::new(),
+            };
 
-    let params =
-        Groth16::<Bn254>::generate_random_parameters_with_reduction(circuit.clone(), &mut csprng)?;
-    let proof =
-        Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &params, &mut csprng)?;
+        let bad_hex = service.gen_proof(request("zz", vec![SIBLING], vec![false], ""));
+        assert_eq!(bad_hex.error_code, 1);
+        assert!(bad_hex.error.contains("leaf"), "{}", bad_hex.error);
+        assert!(bad_hex.envelope.proof.is_empty());
+
+        let odd_length = service.gen_proof(request(LEAF, vec!["abc"], vec![false], ""));
+        assert_eq!(odd_length.error_code, 1);
+        assert!(
+            odd_length.error.contains("siblings"),
+            "{}",
+            odd_length.error
+        );
+
+        let mismatch = service.gen_proof(request(LEAF, vec![SIBLING], vec![false, true], ""));
+        assert_eq!(mismatch.error_code, 2);
+
+        // Above the field modulus: rejected rather than silently reduced.
+        let non_canonical =
+            service.gen_proof(request(&"ff".repeat(32), vec![SIBLING], vec![false], ""));
+        assert_eq!(non_canonical.error_code, 13);
+        assert!(
+            non_canonical.error.contains("leaf"),
+            "{}",
+            non_canonical.error
+        );
+
+        let wrong_root = service.gen_proof(request(LEAF, vec![SIBLING], vec![false], SIBLING));
+        assert_eq!(wrong_root.error_code, 4);
+
+        let mut unknown_circuit = request(LEAF, vec![SIBLING], vec![false], "");
+        unknown_circuit.circuit_id = "range_proof".to_string();
+        assert_eq!(service.gen_proof(unknown_circuit).error_code, 3);
+
+        let depth = crate::keys::MAX_DEPTH + 1;
+        let too_deep =
+            service.gen_proof(request(LEAF, vec![SIBLING; depth], vec![false; depth], ""));
+        assert_eq!(too_deep.error_code, 20);
+    }
 
-    let proof_serialized = serialize_proof(&proof)?;
-    let deserialized_proof = deserialize_proof(&proof_serialized)?;
-    let proof_coordinates = get_proof_coordinates(&deserialized_proof);
+    #[marine_test(config_path = "../../../../.fluence/service-configs/myService.toml")]
+    fn test_verify_proof_errors(service: marine_test_env::myService::ModuleInterface) {
+        use marine_test_env::myService::{ProofEnvelope, VerifyRequest};
 
-    let result = json!({
-        "proof": proof_serialized,
-        "coordinates": proof_coordinates
-    });
+        let request = |proof: &str, key_id: &str| VerifyRequest {
+            envelope: ProofEnvelope {
+                circuit_id: "merkle_membership".to_string(),
+                proof: proof.to_string(),
+                ..Default::default()
+            },
+            public_inputs: vec![LEAF.to_string()],
+            verifying_key: String::new(),
+            key_id: key_id.to_string(),
+        };
 
-    Ok(result.to_string())
-}
+        let unknown_key = service.verify_proof(request("00", "deadbeef"));
+        assert_eq!(unknown_key.error_code, 8);
+        assert!(!unknown_key.valid);
 
-fn get_proof_coordinates(proof: &Proof<Bn254>) -> serde_json::Value {
-    json!({
-        "a": {
-            "x": proof.a.x.to_string(),
-            "y": proof.a.y.to_string()
-        },
-        "b": {
-            "x": {
-                "c0": proof.b.x.c0.to_string(),
-                "c1": proof.b.x.c1.to_string()
-            },
-            "y": {
-                "c0": proof.b.y.c0.to_string(),
-                "c1": proof.b.y.c1.to_string()
-            }
-        },
-        "c": {
-            "x": proof.c.x.to_string(),
-            "y": proof.c.y.to_string()
-        }
-    })
-}
+        let mut bad_key = request("00", "");
+        bad_key.verifying_key = "xyz".to_string();
+        assert_eq!(service.verify_proof(bad_key).error_code, 1);
+    }
 
-#[marine]
-fn gen_proof(leaf_hex: String, sibling_hex: String) -> String {
-    match try_gen_proof(&leaf_hex, &sibling_hex) {
-        Ok(result) => result,
-        Err(e) => format!("Error: {}", e),
+    #[marine_test(config_path = "../../../../.fluence/service-configs/myService.toml")]
+    fn test_proving_metrics(service: marine_test_env::myService::ModuleInterface) {
+        use marine_test_env::myService::ProofRequest;
+
+        let metrics = service.proving_metrics(ProofRequest {
+            leaf: LEAF.to_string(),
+            siblings: vec![SIBLING.to_string()],
+            path_bits: vec![false],
+            root: String::new(),
+            circuit_id: String::new(),
+        });
+        assert_eq!(metrics.error_code, 0, "{}", metrics.error);
+        assert!(metrics.verified);
+        assert_eq!(metrics.depth, 1);
+        assert_eq!(metrics.num_public_inputs, 1);
+        assert!(metrics.num_constraints > 0);
+        assert!(metrics.proving_key_bytes > 0);
     }
-}
 
-fn try_gen_proof(leaf_hex: &str, sibling_hex: &str) -> Result<String, Box<dyn Error>> {
-    let data = parse_data(leaf_hex, sibling_hex)?;
-    let is_valid = verify_merkle_tree(&data)?;
+    #[marine_test(config_path = "../../../../.fluence/service-configs/myService.toml")]
+    fn test_gen_proofs_batch(service: marine_test_env::myService::ModuleInterface) {
+        use marine_test_env::myService::{BatchProofRequest, MembershipWitness};
 
-    let proof = generate_proof(&data)?;
-    Ok(proof)
+        let witness = |leaf: &str, sibling: &str, path_bit: bool| MembershipWitness {
+            leaf: leaf.to_string(),
+            siblings: vec![sibling.to_string()],
+            path_bits: vec![path_bit],
+        };
+
+        let response = service.gen_proofs_batch(BatchProofRequest {
+            items: vec![
+                witness(LEAF, SIBLING, true),
+                witness("zz", SIBLING, false),
+                witness(SIBLING, LEAF, false),
+                witness(SIBLING, LEAF, true),
+            ],
+            root: String::new(),
+            circuit_id: String::new(),
+        });
+        assert_eq!(response.error_code, 0, "{}", response.error);
+        assert!(!response.verifying_key.is_empty());
+
+        let codes: Vec<u32> = response.results.iter().map(|r| r.error_code).collect();
+        assert_eq!(codes, vec![0, 1, 0, 4]);
+        assert_eq!(
+            response.results[0].public_inputs,
+            vec![response.root.clone()]
+        );
+    }
 }
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_groth16::{prepare_verifying_key, Groth16};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::thread_rng;
+use tracing::instrument;
+
+use zk_core::{DataVerificationEntry, MerkleTreeData};
+
+use crate::records::ProvingMetrics;
+use crate::ServiceError;
+
+/// Runs a fresh setup, witness generation, proving and verification for
+/// `data` under `root` and measures each stage. The cached proving keys are
+/// neither used nor touched, so the setup time is that of a cold worker.
+#[instrument(name = "metrics", skip_all, fields(depth = data.siblings.len()))]
+pub fn measure(data: &MerkleTreeData, root: ArkFr) -> Result<ProvingMetrics, ServiceError> {
+    let depth = data.siblings.len();
+    let circuit = DataVerificationEntry::new(data, root)?;
+    let mut rng = thread_rng();
+
+    let start = Instant::now();
+    let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+        DataVerificationEntry::blank(depth)?,
+        &mut rng,
+    )?;
+    let setup = start.elapsed();
+
+    // Synthesize once on its own, the way the prover does, to count the
+    // constraint system and time witness generation.
+    let start = Instant::now();
+    let cs = ConstraintSystem::<ArkFr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Prove {
+        construct_matrices: false,
+    });
+    circuit.clone().generate_constraints(cs.clone())?;
+    cs.finalize();
+    let witness_generation = start.elapsed();
+
+    let start = Instant::now();
+    let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, &mut rng)?;
+    let proving = start.elapsed();
+
+    let start = Instant::now();
+    let verified = Groth16::<Bn254>::verify_proof(&prepare_verifying_key(&pk.vk), &proof, &[root])?;
+    let verification = start.elapsed();
+
+    Ok(ProvingMetrics {
+        depth: depth as u32,
+        num_constraints: cs.num_constraints() as u64,
+        num_witness_variables: cs.num_witness_variables() as u64,
+        num_public_inputs: (cs.num_instance_variables() - 1) as u64,
+        setup_ms: millis(setup),
+        witness_generation_ms: millis(witness_generation),
+        proving_ms: millis(proving),
+        verification_ms: millis(verification),
+        proving_key_bytes: pk.uncompressed_size() as u64,
+        verified,
+        error_code: 0,
+        error: String::new(),
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,317 @@
+use std::convert::TryFrom;
+
+use chrono::{DateTime, Utc};
+use marine_rs_sdk::marine;
+use zk_core::ZkError;
+
+/// Membership witness for one leaf, as sent from Aqua.
+///
+/// All field elements are little-endian hex, the same encoding wasm-lib uses
+/// for its `merkle_proofs`.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct ProofRequest {
+    pub leaf: String,
+    /// Sibling hashes from the leaf level up to the root.
+    pub siblings: Vec<String>,
+    /// `true` when the current node is the left child at that level.
+    pub path_bits: Vec<bool>,
+    /// Expected Merkle root. Left empty, the root the path hashes to is used.
+    pub root: String,
+    /// Circuit to prove with. Left empty, `merkle_membership` is used.
+    pub circuit_id: String,
+}
+
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct G1Coordinates {
+    pub x: String,
+    pub y: String,
+}
+
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct Fq2Coordinates {
+    pub c0: String,
+    pub c1: String,
+}
+
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct G2Coordinates {
+    pub x: Fq2Coordinates,
+    pub y: Fq2Coordinates,
+}
+
+/// Affine coordinates of a Groth16 proof in decimal, ready for a Solidity
+/// verifier.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct ProofCoordinates {
+    pub a: G1Coordinates,
+    pub b: G2Coordinates,
+    pub c: G1Coordinates,
+}
+
+/// Signature over an envelope. An empty `algorithm` means unsigned.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeSignature {
+    pub algorithm: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// `zk_core::ProofEnvelope` as a Marine record: byte fields are hex and
+/// `created_at` is RFC 3339.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct ProofEnvelope {
+    pub version: u32,
+    pub curve: String,
+    pub proving_system: String,
+    pub circuit_id: String,
+    /// Hex SHA-256 of the uncompressed verifying key.
+    pub vk_id: String,
+    /// Little-endian hex, in circuit order.
+    pub public_inputs: Vec<String>,
+    /// Uncompressed `Proof<Bn254>`, hex encoded.
+    pub proof: String,
+    pub created_at: String,
+    /// Worker that produced the proof.
+    pub prover: String,
+    pub signature: EnvelopeSignature,
+}
+
+impl From<zk_core::ProofEnvelope> for ProofEnvelope {
+    fn from(envelope: zk_core::ProofEnvelope) -> Self {
+        ProofEnvelope {
+            version: envelope.version,
+            curve: envelope.curve,
+            proving_system: envelope.proving_system,
+            circuit_id: envelope.circuit_id,
+            vk_id: envelope.vk_id,
+            public_inputs: envelope.public_inputs,
+            proof: hex::encode(envelope.proof),
+            created_at: envelope.created_at.to_rfc3339(),
+            prover: envelope.prover,
+            signature: envelope
+                .signature
+                .map(|signature| EnvelopeSignature {
+                    algorithm: signature.algorithm,
+                    public_key: hex::encode(signature.public_key),
+                    signature: hex::encode(signature.signature),
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<&ProofEnvelope> for zk_core::ProofEnvelope {
+    type Error = ZkError;
+
+    fn try_from(envelope: &ProofEnvelope) -> Result<Self, ZkError> {
+        let decode = |field, value: &str| {
+            hex::decode(value).map_err(|source| ZkError::InvalidHex { field, source })
+        };
+        let created_at = DateTime::parse_from_rfc3339(&envelope.created_at)
+            .map_err(|e| ZkError::InvalidInput {
+                field: "created_at",
+                reason: e.to_string(),
+            })?
+            .with_timezone(&Utc);
+        let signature = if envelope.signature.algorithm.is_empty() {
+            None
+        } else {
+            Some(zk_core::EnvelopeSignature {
+                algorithm: envelope.signature.algorithm.clone(),
+                public_key: decode("public_key", &envelope.signature.public_key)?,
+                signature: decode("signature", &envelope.signature.signature)?,
+            })
+        };
+
+        let envelope = zk_core::ProofEnvelope {
+            version: envelope.version,
+            curve: envelope.curve.clone(),
+            proving_system: envelope.proving_system.clone(),
+            circuit_id: envelope.circuit_id.clone(),
+            vk_id: envelope.vk_id.clone(),
+            public_inputs: envelope.public_inputs.clone(),
+            proof: decode("proof", &envelope.proof)?,
+            created_at,
+            prover: envelope.prover.clone(),
+            signature,
+        };
+        envelope.check_format()?;
+        Ok(envelope)
+    }
+}
+
+/// One membership witness of a `gen_proofs_batch` call.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct MembershipWitness {
+    pub leaf: String,
+    pub siblings: Vec<String>,
+    pub path_bits: Vec<bool>,
+}
+
+/// Many witnesses for the same root, proven with one proving key.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct BatchProofRequest {
+    pub items: Vec<MembershipWitness>,
+    /// Shared Merkle root. Left empty, the root of the first well-formed item
+    /// is used.
+    pub root: String,
+    pub circuit_id: String,
+}
+
+/// Result of `gen_proof`. On failure `error_code` is non-zero, `error` holds
+/// the message and the remaining fields are empty.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct ProofResponse {
+    pub envelope: ProofEnvelope,
+    /// Public inputs in little-endian hex, in circuit order.
+    pub public_inputs: Vec<String>,
+    pub coordinates: ProofCoordinates,
+    /// Uncompressed `VerifyingKey<Bn254>`, hex encoded, so that other workers
+    /// can check the proof.
+    pub verifying_key: String,
+    pub error_code: u32,
+    pub error: String,
+}
+
+impl ProofResponse {
+    pub fn failure(error_code: u32, error: String) -> Self {
+        ProofResponse {
+            error_code,
+            error,
+            ..Default::default()
+        }
+    }
+}
+
+/// Input of `verify_proof`. The verifying key is taken from `verifying_key`
+/// when set, otherwise it is looked up by `key_id` (or the envelope's
+/// `vk_id`) among the keys this worker has produced.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct VerifyRequest {
+    pub envelope: ProofEnvelope,
+    /// Left empty, the envelope's public inputs are used.
+    pub public_inputs: Vec<String>,
+    pub verifying_key: String,
+    pub key_id: String,
+}
+
+/// Result of `verify_proof`. `valid` is only meaningful when `error_code` is
+/// zero; a well-formed proof that does not verify is `valid: false` with no
+/// error.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct Verdict {
+    pub valid: bool,
+    /// Id of the verifying key the proof was checked against.
+    pub vk_id: String,
+    /// Hex public key that signed the envelope, empty if it is unsigned. A
+    /// signature that does not match is an error.
+    pub signer: String,
+    pub error_code: u32,
+    pub error: String,
+}
+
+impl Verdict {
+    pub fn failure(error_code: u32, error: String) -> Self {
+        Verdict {
+            error_code,
+            error,
+            ..Default::default()
+        }
+    }
+}
+
+/// Public key this worker signs its proof envelopes with, as reported by
+/// `attestation_key`.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct AttestationKey {
+    pub algorithm: String,
+    /// Hex encoded.
+    pub public_key: String,
+    pub error_code: u32,
+    pub error: String,
+}
+
+/// One proving key known to the worker, as reported by `key_info`.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct KeyInfo {
+    pub circuit_id: String,
+    pub depth: u32,
+    /// Hex SHA-256 of the circuit's R1CS matrices at this depth.
+    pub circuit_fingerprint: String,
+    pub vk_id: String,
+    /// Key file the proving key was loaded from, empty if it was generated
+    /// by the worker itself.
+    pub path: String,
+    pub error_code: u32,
+    pub error: String,
+}
+
+/// Result of `gen_proofs_batch`. `results` has one entry per item, in order;
+/// each carries its own error code. The verifying key is given once here
+/// rather than in every item.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct BatchProofResponse {
+    pub root: String,
+    pub vk_id: String,
+    pub verifying_key: String,
+    pub results: Vec<ProofResponse>,
+    pub error_code: u32,
+    pub error: String,
+}
+
+impl BatchProofResponse {
+    pub fn failure(error_code: u32, error: String) -> Self {
+        BatchProofResponse {
+            error_code,
+            error,
+            ..Default::default()
+        }
+    }
+}
+
+/// Result of `proving_metrics`: the circuit's size at the request's depth and
+/// the wall-clock time of each stage, in milliseconds. `proving_ms` includes
+/// the prover's own synthesis pass.
+#[marine]
+#[derive(Debug, Clone, Default)]
+pub struct ProvingMetrics {
+    pub depth: u32,
+    pub num_constraints: u64,
+    pub num_witness_variables: u64,
+    /// Public inputs, not counting the constant `1` variable.
+    pub num_public_inputs: u64,
+    pub setup_ms: f64,
+    pub witness_generation_ms: f64,
+    pub proving_ms: f64,
+    pub verification_ms: f64,
+    /// Uncompressed size of the proving key.
+    pub proving_key_bytes: u64,
+    pub verified: bool,
+    pub error_code: u32,
+    pub error: String,
+}
+
+impl ProvingMetrics {
+    pub fn failure(error_code: u32, error: String) -> Self {
+        ProvingMetrics {
+            error_code,
+            error,
+            ..Default::default()
+        }
+    }
+}
@@ -51,6 +51,68 @@ contract ProofStorage is Verifier {
     }
 }
 
+// Verifier for the `upload_ownership` circuit, as printed by `zk export-solidity`
+interface IOwnershipVerifier {
+    function verifyProof(
+        uint[2] memory a,
+        uint[2][2] memory b,
+        uint[2] memory c,
+        uint[4] memory input
+    ) external view returns (bool);
+}
+
+contract OwnershipRegistry {
+    // Public inputs are (root, nullifier, externalNullifier, signal)
+    IOwnershipVerifier public immutable verifier;
+    address public immutable admin;
+
+    // Roots of the committed identity trees
+    mapping(uint => bool) public knownRoots;
+    // External nullifiers proofs may use, one per context such as an upload
+    mapping(uint => bool) public allowedContexts;
+    // Nullifiers already used, so each owner proves once per context
+    mapping(uint => bool) public usedNullifiers;
+
+    event RootAdded(uint root);
+    event ContextAdded(uint externalNullifier);
+    event OwnershipProven(uint indexed externalNullifier, uint nullifier, uint signal);
+
+    constructor(IOwnershipVerifier _verifier) {
+        verifier = _verifier;
+        admin = msg.sender;
+    }
+
+    // Function to publish the root of an identity tree
+    function addRoot(uint root) public {
+        require(msg.sender == admin, "only admin");
+        knownRoots[root] = true;
+        emit RootAdded(root);
+    }
+
+    // Function to open a context in which each owner may prove once
+    function addContext(uint externalNullifier) public {
+        require(msg.sender == admin, "only admin");
+        allowedContexts[externalNullifier] = true;
+        emit ContextAdded(externalNullifier);
+    }
+
+    // Function to prove ownership without revealing the sender's identity leaf
+    function proveOwnership(
+        uint[2] memory a,
+        uint[2][2] memory b,
+        uint[2] memory c,
+        uint[4] memory input
+    ) public {
+        require(knownRoots[input[0]], "unknown root");
+        // Otherwise a prover could pick a fresh context for every proof
+        require(allowedContexts[input[2]], "unknown context");
+        require(!usedNullifiers[input[1]], "nullifier already used");
+        require(verifier.verifyProof(a, b, c, input), "invalid proof");
+        usedNullifiers[input[1]] = true;
+        emit OwnershipProven(input[2], input[1], input[3]);
+    }
+}
+
 
 
 
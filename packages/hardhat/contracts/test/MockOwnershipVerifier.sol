@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import "../Zkproof.sol";
+
+// Stands in for the generated ownership verifier in tests
+contract MockOwnershipVerifier is IOwnershipVerifier {
+    bool public accept = true;
+
+    // Function to choose what every proof verifies as
+    function setAccept(bool _accept) public {
+        accept = _accept;
+    }
+
+    function verifyProof(
+        uint[2] memory,
+        uint[2][2] memory,
+        uint[2] memory,
+        uint[4] memory
+    ) external view override returns (bool) {
+        return accept;
+    }
+}
@@ -0,0 +1,62 @@
+import { expect } from "chai";
+import { ethers } from "hardhat";
+import { MockOwnershipVerifier, OwnershipRegistry } from "../typechain-types";
+
+describe("OwnershipRegistry", function () {
+  const ROOT = 1001n;
+  const CONTEXT = 7n;
+  const a: [bigint, bigint] = [0n, 0n];
+  const b: [[bigint, bigint], [bigint, bigint]] = [
+    [0n, 0n],
+    [0n, 0n],
+  ];
+  const c: [bigint, bigint] = [0n, 0n];
+
+  let verifier: MockOwnershipVerifier;
+  let registry: OwnershipRegistry;
+  beforeEach(async () => {
+    const verifierFactory = await ethers.getContractFactory("MockOwnershipVerifier");
+    verifier = (await verifierFactory.deploy()) as MockOwnershipVerifier;
+    await verifier.waitForDeployment();
+    const registryFactory = await ethers.getContractFactory("OwnershipRegistry");
+    registry = (await registryFactory.deploy(await verifier.getAddress())) as OwnershipRegistry;
+    await registry.waitForDeployment();
+    await registry.addRoot(ROOT);
+    await registry.addContext(CONTEXT);
+  });
+
+  // Public inputs are (root, nullifier, externalNullifier, signal)
+  const input = (nullifier: bigint, context = CONTEXT, root = ROOT): [bigint, bigint, bigint, bigint] => [
+    root,
+    nullifier,
+    context,
+    42n,
+  ];
+
+  it("Should accept one proof per nullifier", async function () {
+    await expect(registry.proveOwnership(a, b, c, input(5n)))
+      .to.emit(registry, "OwnershipProven")
+      .withArgs(CONTEXT, 5n, 42n);
+    expect(await registry.usedNullifiers(5n)).to.equal(true);
+
+    await expect(registry.proveOwnership(a, b, c, input(5n))).to.be.revertedWith("nullifier already used");
+  });
+
+  it("Should reject a context the admin has not opened", async function () {
+    await expect(registry.proveOwnership(a, b, c, input(5n, CONTEXT + 1n))).to.be.revertedWith("unknown context");
+  });
+
+  it("Should reject unknown roots and invalid proofs", async function () {
+    await expect(registry.proveOwnership(a, b, c, input(5n, CONTEXT, ROOT + 1n))).to.be.revertedWith("unknown root");
+
+    await verifier.setAccept(false);
+    await expect(registry.proveOwnership(a, b, c, input(5n))).to.be.revertedWith("invalid proof");
+    expect(await registry.usedNullifiers(5n)).to.equal(false);
+  });
+
+  it("Should only let the admin add roots and contexts", async function () {
+    const [, other] = await ethers.getSigners();
+    await expect(registry.connect(other).addRoot(ROOT + 1n)).to.be.revertedWith("only admin");
+    await expect(registry.connect(other).addContext(CONTEXT + 1n)).to.be.revertedWith("only admin");
+  });
+});
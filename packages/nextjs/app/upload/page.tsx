@@ -1,7 +1,7 @@
 "use client";
 
 import { useEffect, useState } from "react";
-import init, { process_and_hash_csv } from "../../../../rust-modules/wasm-lib/pkg";
+import init, { bytes_to_hex, process_and_redact_csv } from "../../../../rust-modules/wasm-lib/pkg";
 import Dropzone from "./components/Dropzone";
 import lighthouse from "@lighthouse-web3/sdk";
 import { ethers } from "ethers";
@@ -17,6 +17,9 @@ interface ProgressData {
   uploaded: number;
 }
 
+// 31 random bytes, so the secret is always a canonical field element.
+const randomSecret = () => bytes_to_hex(crypto.getRandomValues(new Uint8Array(31)));
+
 const Upload: NextPage = () => {
   const [fileList, setFileList] = useState<FileList | null>(null);
   const { register, handleSubmit } = useForm<{ file: File }>();
@@ -130,12 +133,15 @@ const Upload: NextPage = () => {
           try {
             await init();
 
-            // gets the hash and processed logs
-            const result = process_and_hash_csv(fileContent);
-            const { processed_transactions: logs, hash } = JSON.parse(result);
+            // Redacts personal columns and commits to salted fields derived from
+            // a secret that stays in this browser, for later disclosures
+            const secret = randomSecret();
+            const { processed_transactions, merkle_root } = process_and_redact_csv(fileContent, {}, secret);
+            const root = bytes_to_hex(merkle_root);
+            localStorage.setItem(`upload-secret:${root}`, secret);
 
-            // Call the uploadFile mutation function with the hash and logs
-            uploadFile({ hash, logs });
+            // Call the uploadFile mutation function with the commitment and redacted rows
+            uploadFile({ hash: root, logs: JSON.stringify(processed_transactions) });
             uploadToLighthouse();
           } catch (error) {
             console.error("Error processing logs:", error);
@@ -0,0 +1,61 @@
+//! Typed JS boundary for wasm-lib.
+//!
+//! Results are converted with `serde-wasm-bindgen` into plain JS objects
+//! (`Uint8Array` for digests, objects rather than `Map`s for keyed data). The
+//! TypeScript interfaces are generated by `tsify` and land in the `.d.ts`
+//! emitted by `wasm-pack build`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_REDACTION_RULES: &'static str = r#"
+export type TransactionField = "transaction_id" | "date" | "customer_id" | "amount" | "type" | "description";
+export type RedactionRules = Partial<Record<TransactionField, Redaction>>;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "ProcessedTransactions")]
+    pub type JsProcessedTransactions;
+
+    #[wasm_bindgen(typescript_type = "StreamSummary")]
+    pub type JsStreamSummary;
+
+    #[wasm_bindgen(typescript_type = "RedactedTransactions")]
+    pub type JsRedactedTransactions;
+
+    #[wasm_bindgen(typescript_type = "RedactionRules")]
+    pub type JsRedactionRules;
+
+    #[wasm_bindgen(typescript_type = "Disclosure")]
+    pub type JsDisclosure;
+
+    #[wasm_bindgen(typescript_type = "LogEntry[]")]
+    pub type JsLogEntries;
+
+    #[wasm_bindgen(typescript_type = "LogCommitment")]
+    pub type JsLogCommitment;
+
+    #[wasm_bindgen(typescript_type = "LogLineProof")]
+    pub type JsLogLineProof;
+}
+
+/// Serializes `value` into the JS type `J` declared above.
+pub fn to_js<T: Serialize, J: JsCast>(value: &T) -> Result<J, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(value.serialize(&serializer)?.unchecked_into())
+}
+
+/// Lowercase hex encoding of a byte array, e.g. a `merkle_root`.
+#[wasm_bindgen]
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+}
+
+/// Decodes a hex string (without `0x` prefix) into a byte array.
+#[wasm_bindgen]
+pub fn hex_to_bytes(hex_str: &str) -> Result<Vec<u8>, JsValue> {
+    hex::decode(hex_str).map_err(crate::to_js_error)
+}
@@ -1,52 +1,79 @@
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
-use csv::ReaderBuilder;
-use serde_json::Value;
 use poseidon_rs::poseidon;
+use tsify::Tsify;
 
-#[derive(Serialize, Deserialize)]
-struct TransactionRecord {
-    transaction_id: u32,
-    date: String,
-    customer_id: u32,
-    amount: f64,
-    #[serde(rename = "type")]
-    type_: String,
-    description: String,
-}
+mod bindings;
+mod log_integrity;
+mod logs;
+mod merkle;
+mod record;
+mod redaction;
+mod streaming;
+
+use bindings::{to_js, JsProcessedTransactions};
+use merkle::{fr_to_bytes, MerkleTree, MerkleTreeData};
+use record::{parse_records, LeafHasher, TransactionRecord};
+pub use bindings::{bytes_to_hex, hex_to_bytes};
+pub use log_integrity::{
+    check_log_line, commit_log_entries, commit_logs, log_line_proof, prove_log_line, utc_millis,
+    verify_log_line, LogLineProof, PreviousLine, TimestampedEntry,
+};
+pub use logs::{parse_logs, process_logs, LogEntry};
+pub use redaction::{disclose_fields, process_and_redact_csv, verify_disclosure};
+pub use streaming::CsvHasher;
+pub use zk_core::{fr_to_hex, parse_fr};
 
-#[derive(Serialize, Deserialize)]
-struct ProcessedTransactions {
-    processed_transactions: String,
-    hash: Vec<u8>, // Hash as a vector of bytes
+#[derive(Serialize, Deserialize, Tsify)]
+pub struct ProcessedTransactions {
+    processed_transactions: Vec<TransactionRecord>,
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array")]
+    hash: Vec<u8>, // Poseidon hash of the whole upload
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array")]
+    merkle_root: Vec<u8>, // Little-endian root over the per-record leaves
+    merkle_proofs: Vec<MerkleTreeData>, // One inclusion proof per record, in row order
 }
 
+/// Returns the parsed records in clear together with unsalted commitments to
+/// them, for checks that stay on the client. Anything sent to the backend goes
+/// through `process_and_redact_csv` instead.
 #[wasm_bindgen]
-pub fn process_and_hash_csv(csv_data: &str) -> String {
-    // Prepare the CSV reader and iterate over each record.
-    let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(csv_data.as_bytes());
-    let mut records: Vec<TransactionRecord> = Vec::new();
-    for result in reader.deserialize() {
-        if let Ok(record) = result {
-            records.push(record);
-        }
-    }
-
-    // Serialize the records into a JSON string
-    let transactions_json = serde_json::to_string(&records).unwrap();
-
-    // Serialize the JSON string to a Value
-    let json_value: Value = serde_json::from_str(&transactions_json).unwrap();
-
-    // Hash the JSON value using the Poseidon hash function
-    let hash = poseidon::poseidon(&json_value);
-
-    // Serialize the processed data and the byte array hash to JSON
-    serde_json::to_string(&ProcessedTransactions {
-        processed_transactions: transactions_json,
+pub fn process_and_hash_csv(csv_data: &str) -> Result<JsProcessedTransactions, JsValue> {
+    to_js(&hash_csv(csv_data).map_err(to_js_error)?)
+}
+
+/// Parses, hashes and commits to a CSV upload; see `process_and_hash_csv`.
+pub fn hash_csv(csv_data: &str) -> Result<ProcessedTransactions, Box<dyn std::error::Error>> {
+    // Parse every well-formed record, skipping the rest.
+    let records = parse_records(csv_data);
+
+    // Hash the records using the Poseidon hash function
+    let hash = poseidon::poseidon(&serde_json::to_value(&records)?);
+
+    // Commit to every record individually so single rows can be proven later
+    let tree = build_record_tree(&records)?;
+
+    Ok(ProcessedTransactions {
+        processed_transactions: records,
         hash,
+        merkle_root: fr_to_bytes(&tree.root()),
+        merkle_proofs: tree.proofs(),
     })
-    .unwrap()
+}
+
+fn build_record_tree(
+    records: &[TransactionRecord],
+) -> Result<MerkleTree, light_poseidon::PoseidonError> {
+    let mut hasher = LeafHasher::new()?;
+    let leaves = records
+        .iter()
+        .map(|record| hasher.leaf(record))
+        .collect::<Result<Vec<_>, _>>()?;
+    MerkleTree::new(leaves)
+}
+
+fn to_js_error<E: std::fmt::Display>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
 }
\ No newline at end of file
@@ -0,0 +1,402 @@
+//! Tamper-evident commitments to parsed access logs.
+//!
+//! Every entry is keyed by its time in UTC, and the leaf
+//! `Poseidon(utc_millis, H(entry))` is committed in log order to the same
+//! Poseidon Merkle tree as CSV uploads. Committing fails unless the times never
+//! decrease, so a root vouches for an ordered history. A `LogLineProof` opens
+//! one line together with the leaf before it, whose time must not be later:
+//! inserting, dropping or reordering lines changes the root, and a proof
+//! cannot place a line anywhere but where it was committed.
+
+use ark_bn254::Fr;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+use zk_core::range::signed_fr;
+use zk_core::{fr_to_hex, parse_fr};
+
+use crate::bindings::{to_js, JsLogCommitment, JsLogLineProof};
+use crate::logs::{parse_logs, LogEntry};
+use crate::merkle::{fr_to_bytes, root_from_bytes, MerkleTree, MerkleTreeData};
+use crate::record::LeafHasher;
+
+/// UTC offsets in minutes of the zone abbreviations found in access logs.
+/// Ambiguous abbreviations take their most common reading: `IST` is India,
+/// `CST` is US Central.
+const ZONE_OFFSETS: [(&str, i64); 30] = [
+    ("UTC", 0),
+    ("GMT", 0),
+    ("WET", 0),
+    ("BST", 60),
+    ("CET", 60),
+    ("WEST", 60),
+    ("CEST", 120),
+    ("EET", 120),
+    ("EEST", 180),
+    ("MSK", 180),
+    ("IST", 330),
+    ("HKT", 480),
+    ("SGT", 480),
+    ("JST", 540),
+    ("KST", 540),
+    ("AEST", 600),
+    ("AEDT", 660),
+    ("NZST", 720),
+    ("NZDT", 780),
+    ("HST", -600),
+    ("AKST", -540),
+    ("AKDT", -480),
+    ("PST", -480),
+    ("PDT", -420),
+    ("MST", -420),
+    ("MDT", -360),
+    ("CST", -360),
+    ("CDT", -300),
+    ("EST", -300),
+    ("EDT", -240),
+];
+
+/// A committed log line with its normalized time and the hash of its fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct TimestampedEntry {
+    /// Milliseconds since the Unix epoch, UTC.
+    pub utc_millis: i64,
+    pub entry_hash: String,
+    pub entry: LogEntry,
+}
+
+#[derive(Serialize, Tsify)]
+pub struct LogCommitment {
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array")]
+    merkle_root: Vec<u8>, // Little-endian root over the entries in log order
+    entries: Vec<TimestampedEntry>,
+}
+
+/// The leaf committed just before a proven line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct PreviousLine {
+    pub utc_millis: i64,
+    pub entry_hash: String,
+    pub proof: MerkleTreeData,
+}
+
+/// Everything needed to check that `entry` is line `index` of a committed log
+/// and is not earlier than the line before it. `previous` is absent only for
+/// the first line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct LogLineProof {
+    pub index: usize,
+    pub entry: LogEntry,
+    pub proof: MerkleTreeData,
+    pub previous: Option<PreviousLine>,
+}
+
+/// Parses the logs and commits to every well-formed line in order.
+#[wasm_bindgen]
+pub fn commit_logs(log_data: &str) -> Result<JsLogCommitment, JsValue> {
+    let log = CommittedLog::new(parse_logs(log_data)).map_err(|e| JsValue::from_str(&e))?;
+    to_js(&LogCommitment {
+        merkle_root: fr_to_bytes(&log.tree.root()),
+        entries: log.entries,
+    })
+}
+
+/// Proves line `index`, counted over the well-formed lines only.
+#[wasm_bindgen]
+pub fn prove_log_line(log_data: &str, index: usize) -> Result<JsLogLineProof, JsValue> {
+    to_js(&log_line_proof(log_data, index).map_err(|e| JsValue::from_str(&e))?)
+}
+
+/// Checks a line proof against the root returned by `commit_logs`.
+#[wasm_bindgen]
+pub fn verify_log_line(proof: JsLogLineProof, merkle_root: &[u8]) -> Result<bool, JsValue> {
+    let proof: LogLineProof = serde_wasm_bindgen::from_value(proof.into())?;
+    let root = root_from_bytes(merkle_root).map_err(crate::to_js_error)?;
+    check_log_line(&proof, &root).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Converts an entry's local time to milliseconds since the Unix epoch.
+pub fn utc_millis(entry: &LogEntry) -> Result<i64, String> {
+    let offset = ZONE_OFFSETS
+        .iter()
+        .find(|(zone, _)| *zone == entry.timezone)
+        .map(|(_, offset)| *offset)
+        .ok_or_else(|| format!("unknown time zone: {}", entry.timezone))?;
+    let invalid = || format!("invalid timestamp: {}", entry.timestamp);
+
+    let (date, time) = entry.timestamp.split_once(' ').ok_or_else(invalid)?;
+    let date = parse_numbers(date, &['-']).ok_or_else(invalid)?;
+    let time = parse_numbers(time, &[':', '.']).ok_or_else(invalid)?;
+    let (year, month, day, hour, minute, second, millis) = match (&date[..], &time[..]) {
+        ([year, month, day], [hour, minute, second, millis]) => {
+            (*year, *month, *day, *hour, *minute, *second, *millis)
+        }
+        _ => return Err(invalid()),
+    };
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+        || millis > 999
+    {
+        return Err(invalid());
+    }
+
+    let minutes = (days_from_civil(year, month, day) * 24 + hour) * 60 + minute - offset;
+    Ok((minutes * 60 + second) * 1000 + millis)
+}
+
+/// Unsigned decimal fields separated by any of `separators`.
+fn parse_numbers(text: &str, separators: &[char]) -> Option<Vec<i64>> {
+    text.split(separators)
+        .map(|field| {
+            if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            field.parse().ok()
+        })
+        .collect()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Hashes the entry's JSON form, so every field including a missing message
+/// is bound.
+fn entry_hash(hasher: &mut LeafHasher, entry: &LogEntry) -> Result<Fr, String> {
+    let json = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    hasher.hash_str(&json).map_err(|e| e.to_string())
+}
+
+fn line_leaf(hasher: &mut LeafHasher, utc_millis: i64, entry_hash: Fr) -> Result<Fr, String> {
+    hasher
+        .hash_pair(signed_fr(utc_millis), entry_hash)
+        .map_err(|e| e.to_string())
+}
+
+struct CommittedLog {
+    entries: Vec<TimestampedEntry>,
+    tree: MerkleTree,
+}
+
+impl CommittedLog {
+    /// Fails on an unknown time zone or a line earlier than the one before it.
+    fn new(entries: Vec<LogEntry>) -> Result<Self, String> {
+        let mut hasher = LeafHasher::new().map_err(|e| e.to_string())?;
+        let mut committed: Vec<TimestampedEntry> = Vec::with_capacity(entries.len());
+        let mut leaves = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.into_iter().enumerate() {
+            let utc_millis = utc_millis(&entry)?;
+            if let Some(previous) = committed.last() {
+                if previous.utc_millis > utc_millis {
+                    return Err(format!(
+                        "log line {} at {} {} is earlier than the line before it",
+                        index, entry.timestamp, entry.timezone
+                    ));
+                }
+            }
+            let entry_hash = entry_hash(&mut hasher, &entry)?;
+            leaves.push(line_leaf(&mut hasher, utc_millis, entry_hash)?);
+            committed.push(TimestampedEntry {
+                utc_millis,
+                entry_hash: fr_to_hex(&entry_hash),
+                entry,
+            });
+        }
+        Ok(CommittedLog {
+            entries: committed,
+            tree: MerkleTree::new(leaves).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+pub fn commit_log_entries(log_data: &str) -> Result<(Fr, Vec<TimestampedEntry>), String> {
+    let log = CommittedLog::new(parse_logs(log_data))?;
+    Ok((log.tree.root(), log.entries))
+}
+
+pub fn log_line_proof(log_data: &str, index: usize) -> Result<LogLineProof, String> {
+    let log = CommittedLog::new(parse_logs(log_data))?;
+    let out_of_range = || "log line index out of range".to_string();
+    let line = log.entries.get(index).ok_or_else(out_of_range)?;
+    let proof = log.tree.proof(index).ok_or_else(out_of_range)?;
+
+    let previous = match index.checked_sub(1) {
+        Some(previous_index) => Some(PreviousLine {
+            utc_millis: log.entries[previous_index].utc_millis,
+            entry_hash: log.entries[previous_index].entry_hash.clone(),
+            proof: log.tree.proof(previous_index).ok_or_else(out_of_range)?,
+        }),
+        None => None,
+    };
+
+    Ok(LogLineProof {
+        index,
+        entry: line.entry.clone(),
+        proof,
+        previous,
+    })
+}
+
+/// Checks that the line hashes to the proven leaf at `index` under `root`,
+/// and that the leaf just before it is also under `root` and not later.
+/// Unknown time zones and malformed proofs never verify.
+pub fn check_log_line(line: &LogLineProof, root: &Fr) -> Result<bool, String> {
+    let utc_millis = match utc_millis(&line.entry) {
+        Ok(utc_millis) => utc_millis,
+        Err(_) => return Ok(false),
+    };
+    let mut hasher = LeafHasher::new().map_err(|e| e.to_string())?;
+    let entry_hash = entry_hash(&mut hasher, &line.entry)?;
+    let leaf = line_leaf(&mut hasher, utc_millis, entry_hash)?;
+    if parse_fr("leaf", &line.proof.leaf).ok() != Some(leaf)
+        || line.proof.leaf_index() != line.index
+        || !line.proof.verify(root)
+    {
+        return Ok(false);
+    }
+
+    let previous = match (&line.previous, line.index) {
+        (None, 0) => return Ok(true),
+        (Some(previous), index) if index > 0 => previous,
+        _ => return Ok(false),
+    };
+    let previous_hash = match parse_fr("entry_hash", &previous.entry_hash) {
+        Ok(previous_hash) => previous_hash,
+        Err(_) => return Ok(false),
+    };
+    let previous_leaf = line_leaf(&mut hasher, previous.utc_millis, previous_hash)?;
+    Ok(
+        parse_fr("leaf", &previous.proof.leaf).is_ok_and(|leaf| leaf == previous_leaf)
+            && previous.proof.path_bits.len() == line.proof.path_bits.len()
+            && previous.proof.leaf_index() == line.index - 1
+            && previous.proof.verify(root)
+            && previous.utc_millis <= utc_millis,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGS: &str = r#"
+    2024-05-20 08:00:00.000 PDT GET 200 https://example.com/ 10.0.0.1:443
+    2024-05-20 12:34:56.789 EDT POST 200 "Action completed" https://example.com/api/data 192.168.1.25:5050
+    2024-05-20 17:40:00.000 BST PUT 201 https://example.com/item 10.0.0.2:80
+    2024-05-20 18:45:00.000 CEST DELETE 204 https://example.com/item 10.0.0.3:80
+    2024-05-20 22:15:00.000 IST GET 404 https://example.com/missing 10.0.0.4:80
+    2024-05-21 02:00:00.000 KST GET 500 "Upstream failed" https://example.com/api 10.0.0.5:80
+    "#;
+
+    fn entry(timestamp: &str, timezone: &str) -> LogEntry {
+        LogEntry {
+            timestamp: timestamp.to_string(),
+            timezone: timezone.to_string(),
+            method: "GET".to_string(),
+            status: 200,
+            message: None,
+            url: "https://example.com/".to_string(),
+            address: "10.0.0.1:443".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalizes_zones_to_utc() {
+        // 2024-05-20 16:45:00 UTC
+        let utc = 1_716_223_500_000;
+        assert_eq!(
+            utc_millis(&entry("2024-05-20 16:45:00.000", "UTC")),
+            Ok(utc)
+        );
+        assert_eq!(
+            utc_millis(&entry("2024-05-20 12:45:00.000", "EDT")),
+            Ok(utc)
+        );
+        assert_eq!(
+            utc_millis(&entry("2024-05-20 22:15:00.000", "IST")),
+            Ok(utc)
+        );
+        assert_eq!(
+            utc_millis(&entry("2024-05-21 01:45:00.000", "KST")),
+            Ok(utc)
+        );
+        assert_eq!(
+            utc_millis(&entry("2024-01-01 00:00:00.001", "UTC")),
+            Ok(1_704_067_200_001)
+        );
+
+        assert!(utc_millis(&entry("2024-05-20 12:45:00.000", "XYZ")).is_err());
+        assert!(utc_millis(&entry("2023-02-29 00:00:00.000", "UTC")).is_err());
+        assert!(utc_millis(&entry("2024-05-20 24:00:00.000", "UTC")).is_err());
+    }
+
+    #[test]
+    fn test_commit_requires_ordered_lines() {
+        let (_, entries) = commit_log_entries(LOGS).unwrap();
+        assert_eq!(entries.len(), 6);
+        assert!(entries
+            .windows(2)
+            .all(|w| w[0].utc_millis <= w[1].utc_millis));
+
+        // 12:34 EDT is 17:34 BST, after the BST line that follows it.
+        let reordered = LOGS.replace("17:40:00.000 BST", "17:00:00.000 BST");
+        let error = commit_log_entries(&reordered).unwrap_err();
+        assert!(error.contains("log line 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_line_proofs_verify() {
+        let (root, _) = commit_log_entries(LOGS).unwrap();
+        for index in 0..6 {
+            let proof = log_line_proof(LOGS, index).unwrap();
+            assert_eq!(proof.previous.is_some(), index > 0);
+            assert!(check_log_line(&proof, &root).unwrap(), "line {}", index);
+        }
+        assert!(log_line_proof(LOGS, 6).is_err());
+    }
+
+    #[test]
+    fn test_rejects_tampered_history() {
+        let (root, _) = commit_log_entries(LOGS).unwrap();
+        let proof = log_line_proof(LOGS, 3).unwrap();
+
+        let mut edited = proof.clone();
+        edited.entry.status = 200;
+        assert!(!check_log_line(&edited, &root).unwrap());
+
+        let mut moved = proof.clone();
+        moved.index = 4;
+        assert!(!check_log_line(&moved, &root).unwrap());
+
+        // The predecessor must be the committed line just before this one.
+        let mut other_previous = proof.clone();
+        other_previous.previous = log_line_proof(LOGS, 2).unwrap().previous;
+        assert!(!check_log_line(&other_previous, &root).unwrap());
+
+        let mut later_previous = proof.clone();
+        later_previous.previous.as_mut().unwrap().utc_millis += 3_600_000;
+        assert!(!check_log_line(&later_previous, &root).unwrap());
+
+        let edited_history = LOGS.replace("PUT 201", "PUT 202");
+        let (other_root, _) = commit_log_entries(&edited_history).unwrap();
+        assert!(!check_log_line(&proof, &other_root).unwrap());
+    }
+}
@@ -0,0 +1,151 @@
+use std::net::SocketAddr;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::bindings::{to_js, JsLogEntries};
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "DELETE", "PATCH", "OPTIONS", "HEAD"];
+
+/// One HTTP access log line. The fields may appear in any order on the line;
+/// only `message` is optional.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct LogEntry {
+    /// Local time as written, `YYYY-MM-DD HH:MM:SS.mmm`.
+    pub timestamp: String,
+    /// Zone abbreviation following the time, e.g. `EDT`.
+    pub timezone: String,
+    pub method: String,
+    pub status: u16,
+    pub message: Option<String>,
+    pub url: String,
+    /// Client `ip:port`.
+    pub address: String,
+}
+
+/// Parses every well-formed line; blank and malformed lines are skipped.
+pub fn parse_logs(log_data: &str) -> Vec<LogEntry> {
+    let parser = LineParser::new();
+    log_data
+        .lines()
+        .filter_map(|line| parser.parse(line))
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn process_logs(log_data: &str) -> Result<JsLogEntries, JsValue> {
+    to_js(&parse_logs(log_data))
+}
+
+struct LineParser {
+    message: Regex,
+    timestamp: Regex,
+}
+
+impl LineParser {
+    fn new() -> Self {
+        LineParser {
+            message: Regex::new(r#""([^"]*)""#).unwrap(),
+            timestamp: Regex::new(r"\b(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3}) ([A-Z]{2,5})\b")
+                .unwrap(),
+        }
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let mut messages = self.message.captures_iter(line);
+        let message = messages.next().map(|captures| captures[1].to_string());
+        if messages.next().is_some() {
+            return None;
+        }
+        let rest = self.message.replace(line, " ");
+
+        let mut timestamps = self.timestamp.captures_iter(&rest);
+        let captures = timestamps.next()?;
+        if timestamps.next().is_some() {
+            return None;
+        }
+        let (timestamp, timezone) = (captures[1].to_string(), captures[2].to_string());
+        let rest = self.timestamp.replace(&rest, " ");
+
+        let (mut method, mut status, mut url, mut address) = (None, None, None, None);
+        for token in rest.split_whitespace() {
+            let slot = if token.starts_with("http://") || token.starts_with("https://") {
+                set(&mut url, token.to_string())
+            } else if METHODS.contains(&token) {
+                set(&mut method, token.to_string())
+            } else if token.parse::<SocketAddr>().is_ok() {
+                set(&mut address, token.to_string())
+            } else {
+                match token.parse::<u16>() {
+                    Ok(code) if (100..600).contains(&code) => set(&mut status, code),
+                    _ => false,
+                }
+            };
+            if !slot {
+                return None;
+            }
+        }
+
+        Some(LogEntry {
+            timestamp,
+            timezone,
+            method: method?,
+            status: status?,
+            message,
+            url: url?,
+            address: address?,
+        })
+    }
+}
+
+/// Fills an empty slot; `false` if the field was already seen on the line.
+fn set<T>(slot: &mut Option<T>, value: T) -> bool {
+    if slot.is_some() {
+        return false;
+    }
+    *slot = Some(value);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+    2024-05-20 12:34:56.789 EDT POST 200 "Action completed" https://example.com/api/data 192.168.1.25:5050
+    DELETE 192.168.100.100:80 2024-05-22 15:00:00.000 UTC https://example.net/resource 204 "No Content"
+    2024-05-26 23:59:59.999 EST http://example.co.uk/path/to/resource?query=123 404 GET 192.168.1.50:80
+    2024-05-28 03:30:00.000 BST 200 192.168.1.75:22 "All good" GET https://192.168.1.75/api/query
+    "#;
+
+    #[test]
+    fn test_parses_fields_in_any_order() {
+        let entries = parse_logs(SAMPLE);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries[1],
+            LogEntry {
+                timestamp: "2024-05-22 15:00:00.000".to_string(),
+                timezone: "UTC".to_string(),
+                method: "DELETE".to_string(),
+                status: 204,
+                message: Some("No Content".to_string()),
+                url: "https://example.net/resource".to_string(),
+                address: "192.168.100.100:80".to_string(),
+            }
+        );
+        assert_eq!(entries[2].message, None);
+        assert_eq!(entries[3].url, "https://192.168.1.75/api/query");
+    }
+
+    #[test]
+    fn test_skips_malformed_lines() {
+        let log_data = "2024-05-20 12:34:56.789 EDT POST 200 https://example.com\n\
+                        2024-05-20 12:34:56.789 EDT POST 200 999 https://example.com 10.0.0.1:1\n\
+                        2024-05-20 12:34:56.789 EDT GET GET 200 https://example.com 10.0.0.1:1\n\
+                        not a log line";
+        assert!(parse_logs(log_data).is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use light_poseidon::{Poseidon, PoseidonError, PoseidonHasher};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use zk_core::{fr_to_hex, parse_fr, ZkError};
+
+/// Little-endian bytes of a field element: the bytes behind
+/// `zk_core::fr_to_hex`, for digests handed to JS as `Uint8Array`.
+pub fn fr_to_bytes(value: &Fr) -> Vec<u8> {
+    value.into_bigint().to_bytes_le()
+}
+
+/// Decodes a root handed back from JS as bytes. Like `parse_fr`, it rejects
+/// encodings that are not canonical instead of reducing them.
+pub fn root_from_bytes(bytes: &[u8]) -> Result<Fr, ZkError> {
+    parse_fr("merkle_root", &hex::encode(bytes))
+}
+
+/// Number of levels needed for `leaf_count` leaves once padded to a power of two.
+pub fn depth_for(leaf_count: usize) -> usize {
+    leaf_count.max(1).next_power_of_two().trailing_zeros() as usize
+}
+
+/// Roots of all-zero subtrees: `zeros[0] = 0`, `zeros[i + 1] = H(zeros[i], zeros[i])`.
+pub fn zero_hashes(hasher: &mut Poseidon<Fr>, depth: usize) -> Result<Vec<Fr>, PoseidonError> {
+    let mut zeros = Vec::with_capacity(depth + 1);
+    zeros.push(Fr::zero());
+    for level in 0..depth {
+        let zero = zeros[level];
+        zeros.push(hasher.hash(&[zero, zero])?);
+    }
+    Ok(zeros)
+}
+
+/// Append-only Poseidon Merkle accumulator.
+///
+/// Only the left siblings still waiting for a right neighbour are kept (one per
+/// level), so memory is logarithmic in the number of leaves. The tree is padded
+/// with zero leaves up to the next power of two when finalized.
+pub struct MerkleAccumulator {
+    hasher: Poseidon<Fr>,
+    frontier: Vec<Option<Fr>>,
+    leaf_count: usize,
+}
+
+impl MerkleAccumulator {
+    pub fn new() -> Result<Self, PoseidonError> {
+        Ok(MerkleAccumulator {
+            hasher: Poseidon::<Fr>::new_circom(2)?,
+            frontier: Vec::new(),
+            leaf_count: 0,
+        })
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn push(&mut self, leaf: Fr) -> Result<(), PoseidonError> {
+        let mut current = leaf;
+        let mut level = 0;
+        while let Some(left) = self.frontier.get_mut(level).and_then(Option::take) {
+            current = self.hasher.hash(&[left, current])?;
+            level += 1;
+        }
+        if level == self.frontier.len() {
+            self.frontier.push(None);
+        }
+        self.frontier[level] = Some(current);
+        self.leaf_count += 1;
+        Ok(())
+    }
+
+    /// Returns the root and depth of the zero-padded tree.
+    pub fn finalize(mut self) -> Result<(Fr, usize), PoseidonError> {
+        let depth = depth_for(self.leaf_count);
+        let zeros = zero_hashes(&mut self.hasher, depth)?;
+
+        if let Some(Some(root)) = self.frontier.get(depth) {
+            return Ok((*root, depth));
+        }
+
+        let mut current: Option<Fr> = None;
+        for (level, zero) in zeros.iter().enumerate().take(depth) {
+            let pending = self.frontier.get(level).copied().flatten();
+            current = match (pending, current) {
+                (Some(left), Some(right)) => Some(self.hasher.hash(&[left, right])?),
+                (Some(left), None) => Some(self.hasher.hash(&[left, *zero])?),
+                (None, Some(left)) => Some(self.hasher.hash(&[left, *zero])?),
+                (None, None) => None,
+            };
+        }
+
+        Ok((current.unwrap_or(zeros[depth]), depth))
+    }
+}
+
+/// Inclusion proof for a single leaf, in the shape of zk_snarks'
+/// `MerkleTreeData`. Field elements are little-endian hex; `siblings` run from
+/// the leaf level up and `path_bits[i]` is `true` when the node at level `i`
+/// is the left child.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct MerkleTreeData {
+    pub leaf: String,
+    pub siblings: Vec<String>,
+    pub path_bits: Vec<bool>,
+}
+
+impl MerkleTreeData {
+    /// Position of the proven leaf, read back from its path.
+    pub fn leaf_index(&self) -> usize {
+        self.path_bits
+            .iter()
+            .rev()
+            .fold(0, |index, is_left| index * 2 + usize::from(!is_left))
+    }
+
+    /// Recomputes the root from the leaf and path and compares it to `root`.
+    /// Malformed hex or mismatched lengths never verify.
+    pub fn verify(&self, root: &Fr) -> bool {
+        if self.siblings.len() != self.path_bits.len() {
+            return false;
+        }
+        let (mut current, mut hasher) =
+            match (parse_fr("leaf", &self.leaf), Poseidon::<Fr>::new_circom(2)) {
+                (Ok(leaf), Ok(hasher)) => (leaf, hasher),
+                _ => return false,
+            };
+        for (sibling, path_bit) in self.siblings.iter().zip(&self.path_bits) {
+            let sibling = match parse_fr("siblings", sibling) {
+                Ok(sibling) => sibling,
+                Err(_) => return false,
+            };
+            let (left, right) = if *path_bit {
+                (current, sibling)
+            } else {
+                (sibling, current)
+            };
+            current = match hasher.hash(&[left, right]) {
+                Ok(hash) => hash,
+                Err(_) => return false,
+            };
+        }
+        current == *root
+    }
+}
+
+/// Fully materialized Poseidon Merkle tree, padded with zero leaves to the
+/// next power of two. Produces the same root as `MerkleAccumulator`.
+pub struct MerkleTree {
+    levels: Vec<Vec<Fr>>,
+    zeros: Vec<Fr>,
+    /// Kept apart from `levels` so that an empty tree has no leaves to prove.
+    root: Fr,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Fr>) -> Result<Self, PoseidonError> {
+        let mut hasher = Poseidon::<Fr>::new_circom(2)?;
+        let depth = depth_for(leaves.len());
+        let zeros = zero_hashes(&mut hasher, depth)?;
+
+        let mut levels = vec![leaves];
+        for zero in zeros.iter().take(depth) {
+            let below = &levels[levels.len() - 1];
+            let mut level = Vec::with_capacity(below.len().div_ceil(2));
+            for pair in below.chunks(2) {
+                let right = pair.get(1).copied().unwrap_or(*zero);
+                level.push(hasher.hash(&[pair[0], right])?);
+            }
+            levels.push(level);
+        }
+
+        let root = levels[depth].first().copied().unwrap_or(zeros[depth]);
+        Ok(MerkleTree {
+            levels,
+            zeros,
+            root,
+        })
+    }
+
+    pub fn root(&self) -> Fr {
+        self.root
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    /// Inclusion proof for the leaf at `index`, or `None` if out of range.
+    pub fn proof(&self, index: usize) -> Option<MerkleTreeData> {
+        let leaf = *self.levels[0].get(index)?;
+        let mut siblings = Vec::with_capacity(self.depth());
+        let mut path_bits = Vec::with_capacity(self.depth());
+
+        let mut position = index;
+        for (level, zero) in self.levels.iter().zip(&self.zeros).take(self.depth()) {
+            let sibling = level.get(position ^ 1).unwrap_or(zero);
+            siblings.push(fr_to_hex(sibling));
+            path_bits.push(position & 1 == 0);
+            position /= 2;
+        }
+
+        Some(MerkleTreeData {
+            leaf: fr_to_hex(&leaf),
+            siblings,
+            path_bits,
+        })
+    }
+
+    pub fn proofs(&self) -> Vec<MerkleTreeData> {
+        (0..self.levels[0].len())
+            .filter_map(|index| self.proof(index))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_matches_accumulator() {
+        for count in 0..9u64 {
+            let leaves: Vec<Fr> = (1..=count).map(Fr::from).collect();
+            let mut accumulator = MerkleAccumulator::new().unwrap();
+            for leaf in &leaves {
+                accumulator.push(*leaf).unwrap();
+            }
+            let tree = MerkleTree::new(leaves).unwrap();
+            assert_eq!(accumulator.finalize().unwrap(), (tree.root(), tree.depth()));
+        }
+    }
+
+    #[test]
+    fn test_proofs_recompute_root() {
+        let tree = MerkleTree::new((1..=5u64).map(Fr::from).collect()).unwrap();
+
+        for (index, proof) in tree.proofs().iter().enumerate() {
+            assert!(proof.verify(&tree.root()));
+            assert_eq!(proof.leaf_index(), index);
+        }
+        let mut tampered = tree.proof(4).unwrap();
+        tampered.path_bits[0] = !tampered.path_bits[0];
+        assert!(!tampered.verify(&tree.root()));
+        assert!(tree.proof(5).is_none());
+    }
+
+    #[test]
+    fn test_empty_tree_has_no_proofs() {
+        let tree = MerkleTree::new(Vec::new()).unwrap();
+        assert_eq!(tree.root(), Fr::zero());
+        assert!(tree.proof(0).is_none());
+        assert!(tree.proofs().is_empty());
+
+        let output = crate::hash_csv("").unwrap();
+        assert!(output.processed_transactions.is_empty());
+        assert!(output.merkle_proofs.is_empty());
+        assert_eq!(output.merkle_root, fr_to_bytes(&Fr::zero()));
+    }
+}
@@ -0,0 +1,107 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use csv::ReaderBuilder;
+use light_poseidon::{Poseidon, PoseidonError, PoseidonHasher};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use zk_core::range::{fixed_amount, signed_fr};
+
+/// Column names in the order they are committed to.
+pub const FIELD_NAMES: [&str; 6] = [
+    "transaction_id",
+    "date",
+    "customer_id",
+    "amount",
+    "type",
+    "description",
+];
+
+/// Number of bytes packed into a single field element when hashing strings.
+/// 31 bytes always fit below the BN254 scalar modulus.
+const STRING_CHUNK_LEN: usize = 31;
+
+#[derive(Serialize, Deserialize, Tsify)]
+pub struct TransactionRecord {
+    pub transaction_id: u32,
+    pub date: String,
+    pub customer_id: u32,
+    pub amount: f64,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub description: String,
+}
+
+impl TransactionRecord {
+    /// The amount in fixed-point representation (see `zk_core`'s
+    /// `AMOUNT_SCALE`).
+    pub fn amount_fixed(&self) -> i64 {
+        fixed_amount(self.amount)
+    }
+}
+
+/// Parses every well-formed record of a CSV file with a header row;
+/// malformed rows are skipped.
+pub fn parse_records(csv_data: &str) -> Vec<TransactionRecord> {
+    ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(csv_data.as_bytes())
+        .deserialize()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Poseidon hashers reused across records, so the round constants are only
+/// generated once per upload.
+pub struct LeafHasher {
+    pair: Poseidon<Fr>,
+    record: Poseidon<Fr>,
+}
+
+impl LeafHasher {
+    pub fn new() -> Result<Self, PoseidonError> {
+        Ok(LeafHasher {
+            pair: Poseidon::<Fr>::new_circom(2)?,
+            record: Poseidon::<Fr>::new_circom(6)?,
+        })
+    }
+
+    /// Hashes an arbitrary string into a single field element by absorbing it
+    /// in 31-byte chunks, seeded with its length so that trailing zero bytes
+    /// cannot collide.
+    pub fn hash_str(&mut self, value: &str) -> Result<Fr, PoseidonError> {
+        let bytes = value.as_bytes();
+        let mut acc = Fr::from(bytes.len() as u64);
+        for chunk in bytes.chunks(STRING_CHUNK_LEN) {
+            acc = self.pair.hash(&[acc, Fr::from_le_bytes_mod_order(chunk)])?;
+        }
+        Ok(acc)
+    }
+
+    /// Encodes every column of a record as a field element, in `FIELD_NAMES`
+    /// order. Strings are hashed with `hash_str`.
+    pub fn encode_fields(&mut self, record: &TransactionRecord) -> Result<[Fr; 6], PoseidonError> {
+        Ok([
+            Fr::from(record.transaction_id as u64),
+            self.hash_str(&record.date)?,
+            Fr::from(record.customer_id as u64),
+            signed_fr(record.amount_fixed()),
+            self.hash_str(&record.type_)?,
+            self.hash_str(&record.description)?,
+        ])
+    }
+
+    /// Computes the Merkle leaf of a record:
+    /// `Poseidon(transaction_id, H(date), customer_id, amount, H(type), H(description))`.
+    pub fn leaf(&mut self, record: &TransactionRecord) -> Result<Fr, PoseidonError> {
+        let fields = self.encode_fields(record)?;
+        self.hash_fields(&fields)
+    }
+
+    pub fn hash_fields(&mut self, fields: &[Fr; 6]) -> Result<Fr, PoseidonError> {
+        self.record.hash(fields)
+    }
+
+    pub fn hash_pair(&mut self, left: Fr, right: Fr) -> Result<Fr, PoseidonError> {
+        self.pair.hash(&[left, right])
+    }
+}
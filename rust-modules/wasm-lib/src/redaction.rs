@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonError, PoseidonHasher};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+use zk_core::range::{fixed_amount, signed_fr};
+use zk_core::{fr_to_hex, parse_fr};
+
+use crate::bindings::{to_js, JsDisclosure, JsRedactedTransactions, JsRedactionRules};
+use crate::merkle::{fr_to_bytes, root_from_bytes, MerkleTree, MerkleTreeData};
+use crate::record::{parse_records, LeafHasher, TransactionRecord, FIELD_NAMES};
+use crate::to_js_error;
+
+/// What happens to a column before the record leaves the browser.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Tsify)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Redaction {
+    Keep,
+    Drop,
+    /// Replaces all but the last `visible` characters with `*`.
+    Mask {
+        #[serde(default)]
+        visible: usize,
+    },
+    /// Replaces the value with its salted field commitment.
+    SaltedHash,
+    /// Replaces the value with a token that is stable across rows for the
+    /// same secret, so columns can still be joined on.
+    Tokenize,
+}
+
+/// Per-column rules keyed by CSV header, e.g.
+/// `{"date": {"action": "mask", "visible": 2}, "amount": {"action": "salted_hash"}}`.
+/// Columns without a rule follow `default_rule`.
+pub type RedactionRules = HashMap<String, Redaction>;
+
+/// Rule for a column the caller gave none for: free text is dropped and
+/// customer ids are tokenized, so personal data only leaves the browser when a
+/// rule asks for it with `{"action": "keep"}`.
+pub fn default_rule(column: &str) -> Redaction {
+    match column {
+        "description" => Redaction::Drop,
+        "customer_id" => Redaction::Tokenize,
+        _ => Redaction::Keep,
+    }
+}
+
+/// Rejects rules for columns that are not part of `TransactionRecord`.
+pub fn check_rules(rules: RedactionRules) -> Result<RedactionRules, String> {
+    match rules
+        .keys()
+        .find(|column| !FIELD_NAMES.contains(&column.as_str()))
+    {
+        Some(column) => Err(format!("unknown column in redaction rules: {}", column)),
+        None => Ok(rules),
+    }
+}
+
+/// The opening of one committed field: its original value and salt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct FieldOpening {
+    #[tsify(type = "string | number")]
+    pub value: Value,
+    pub salt: String,
+}
+
+/// Selected fields of one row together with everything needed to check them
+/// against the upload's Merkle root. Unopened fields stay hidden behind their
+/// commitments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct Disclosure {
+    pub row_index: usize,
+    pub commitments: Vec<String>,
+    #[tsify(type = "Partial<Record<TransactionField, FieldOpening>>")]
+    pub openings: BTreeMap<String, FieldOpening>,
+    pub proof: MerkleTreeData,
+}
+
+#[derive(Serialize, Tsify)]
+pub struct RedactedTransactions {
+    #[tsify(type = "Array<Partial<Record<TransactionField, string | number>>>")]
+    processed_transactions: Vec<Map<String, Value>>,
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array")]
+    merkle_root: Vec<u8>,
+    merkle_proofs: Vec<MerkleTreeData>,
+}
+
+/// Derives salts, commitments and tokens from a secret that never leaves the
+/// client.
+///
+/// Each field is committed as `Poseidon(value, salt)` with
+/// `salt = Poseidon(secret, row, column)`, and a row's leaf is the Poseidon hash
+/// of its six commitments. Salts can therefore be re-derived for any later
+/// disclosure without being stored.
+pub struct FieldCommitter {
+    leaves: LeafHasher,
+    salts: Poseidon<Fr>,
+    secret: Fr,
+}
+
+impl FieldCommitter {
+    pub fn new(secret: Fr) -> Result<Self, PoseidonError> {
+        Ok(FieldCommitter {
+            leaves: LeafHasher::new()?,
+            salts: Poseidon::<Fr>::new_circom(3)?,
+            secret,
+        })
+    }
+
+    pub fn salt(&mut self, row: usize, column: usize) -> Result<Fr, PoseidonError> {
+        self.salts
+            .hash(&[self.secret, Fr::from(row as u64), Fr::from(column as u64)])
+    }
+
+    pub fn commitments(
+        &mut self,
+        row: usize,
+        record: &TransactionRecord,
+    ) -> Result<[Fr; 6], PoseidonError> {
+        let mut fields = self.leaves.encode_fields(record)?;
+        for (column, field) in fields.iter_mut().enumerate() {
+            let salt = self.salt(row, column)?;
+            *field = self.leaves.hash_pair(*field, salt)?;
+        }
+        Ok(fields)
+    }
+
+    pub fn leaf(&mut self, commitments: &[Fr; 6]) -> Result<Fr, PoseidonError> {
+        self.leaves.hash_fields(commitments)
+    }
+
+    fn token(
+        &mut self,
+        record: &TransactionRecord,
+        column: usize,
+    ) -> Result<String, PoseidonError> {
+        let value = self.leaves.encode_fields(record)?[column];
+        let token = self.leaves.hash_pair(self.secret, value)?;
+        Ok(format!("tok_{}", &fr_to_hex(&token)[..16]))
+    }
+
+    /// Applies `rules` to a record, returning the JSON object that is safe to
+    /// send to the backend.
+    pub fn redact(
+        &mut self,
+        record: &TransactionRecord,
+        commitments: &[Fr; 6],
+        rules: &RedactionRules,
+    ) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+        let mut row = match serde_json::to_value(record)? {
+            Value::Object(row) => row,
+            other => return Err(format!("record is not a JSON object: {}", other).into()),
+        };
+
+        for (column, name) in FIELD_NAMES.iter().enumerate() {
+            let rule = rules
+                .get(*name)
+                .cloned()
+                .unwrap_or_else(|| default_rule(name));
+            let redacted = match rule {
+                Redaction::Keep => continue,
+                Redaction::Drop => {
+                    row.remove(*name);
+                    continue;
+                }
+                Redaction::Mask { visible } => mask(&row[*name], visible),
+                Redaction::SaltedHash => fr_to_hex(&commitments[column]),
+                Redaction::Tokenize => self.token(record, column)?,
+            };
+            row.insert(name.to_string(), Value::String(redacted));
+        }
+        Ok(row)
+    }
+
+    /// Encodes a disclosed JSON value the same way `LeafHasher::encode_fields`
+    /// encodes the record column.
+    fn encode_value(&mut self, column: usize, value: &Value) -> Option<Result<Fr, PoseidonError>> {
+        match FIELD_NAMES[column] {
+            "transaction_id" | "customer_id" => value.as_u64().map(|v| Ok(Fr::from(v))),
+            "amount" => value.as_f64().map(|v| Ok(signed_fr(fixed_amount(v)))),
+            _ => value.as_str().map(|v| self.leaves.hash_str(v)),
+        }
+    }
+}
+
+fn mask(value: &Value, visible: usize) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let hidden = text.chars().count().saturating_sub(visible);
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| if i < hidden { '*' } else { c })
+        .collect()
+}
+
+fn parse_secret(secret_hex: &str) -> Result<Fr, JsValue> {
+    if secret_hex.is_empty() {
+        return Err(JsValue::from_str("secret must not be empty"));
+    }
+    parse_fr("secret", secret_hex).map_err(to_js_error)
+}
+
+/// Redacts every record according to `rules` and commits to the salted field
+/// commitments of the original values.
+///
+/// Returns the redacted rows, the Merkle root over the committed rows and one
+/// inclusion proof per row.
+#[wasm_bindgen]
+pub fn process_and_redact_csv(
+    csv_data: &str,
+    rules: JsRedactionRules,
+    secret_hex: &str,
+) -> Result<JsRedactedTransactions, JsValue> {
+    let rules: RedactionRules = serde_wasm_bindgen::from_value(rules.into())?;
+    let rules = check_rules(rules).map_err(|e| JsValue::from_str(&e))?;
+    to_js(&redact_csv(csv_data, &rules, parse_secret(secret_hex)?).map_err(to_js_error)?)
+}
+
+/// Opens the columns listed in `fields` for row `row_index`.
+#[wasm_bindgen]
+pub fn disclose_fields(
+    csv_data: &str,
+    row_index: usize,
+    fields: Vec<String>,
+    secret_hex: &str,
+) -> Result<JsDisclosure, JsValue> {
+    let disclosure = disclose(csv_data, row_index, &fields, parse_secret(secret_hex)?)
+        .map_err(|e| JsValue::from_str(&e))?;
+    to_js(&disclosure)
+}
+
+/// Checks a disclosure against the upload's Merkle root: every opened value
+/// must match its commitment, the commitments must hash to the proven leaf and
+/// the leaf must be included under `merkle_root` at `row_index`.
+#[wasm_bindgen]
+pub fn verify_disclosure(disclosure: JsDisclosure, merkle_root: &[u8]) -> Result<bool, JsValue> {
+    let disclosure: Disclosure = serde_wasm_bindgen::from_value(disclosure.into())?;
+    let root = root_from_bytes(merkle_root).map_err(to_js_error)?;
+    check_disclosure(&disclosure, &root).map_err(to_js_error)
+}
+
+pub fn redact_csv(
+    csv_data: &str,
+    rules: &RedactionRules,
+    secret: Fr,
+) -> Result<RedactedTransactions, Box<dyn std::error::Error>> {
+    let mut committer = FieldCommitter::new(secret)?;
+
+    let mut rows = Vec::new();
+    let mut leaves = Vec::new();
+    for (index, record) in parse_records(csv_data).iter().enumerate() {
+        let commitments = committer.commitments(index, record)?;
+        leaves.push(committer.leaf(&commitments)?);
+        rows.push(committer.redact(record, &commitments, rules)?);
+    }
+    let tree = MerkleTree::new(leaves)?;
+
+    Ok(RedactedTransactions {
+        processed_transactions: rows,
+        merkle_root: fr_to_bytes(&tree.root()),
+        merkle_proofs: tree.proofs(),
+    })
+}
+
+pub fn disclose(
+    csv_data: &str,
+    row_index: usize,
+    fields: &[String],
+    secret: Fr,
+) -> Result<Disclosure, String> {
+    let mut committer = FieldCommitter::new(secret).map_err(|e| e.to_string())?;
+
+    let records = parse_records(csv_data);
+    let mut leaves = Vec::with_capacity(records.len());
+    let mut row_commitments = None;
+    for (index, record) in records.iter().enumerate() {
+        let commitments = committer
+            .commitments(index, record)
+            .map_err(|e| e.to_string())?;
+        leaves.push(committer.leaf(&commitments).map_err(|e| e.to_string())?);
+        if index == row_index {
+            row_commitments = Some(commitments);
+        }
+    }
+    let commitments = row_commitments.ok_or("row index out of range")?;
+    let proof = MerkleTree::new(leaves)
+        .map_err(|e| e.to_string())?
+        .proof(row_index)
+        .ok_or("row index out of range")?;
+
+    let row = serde_json::to_value(&records[row_index]).map_err(|e| e.to_string())?;
+    let mut openings = BTreeMap::new();
+    for field in fields {
+        let column = FIELD_NAMES
+            .iter()
+            .position(|name| name == field)
+            .ok_or_else(|| format!("unknown column: {}", field))?;
+        let salt = committer
+            .salt(row_index, column)
+            .map_err(|e| e.to_string())?;
+        openings.insert(
+            field.clone(),
+            FieldOpening {
+                value: row[field].clone(),
+                salt: fr_to_hex(&salt),
+            },
+        );
+    }
+
+    Ok(Disclosure {
+        row_index,
+        commitments: commitments.iter().map(fr_to_hex).collect(),
+        openings,
+        proof,
+    })
+}
+
+pub fn check_disclosure(disclosure: &Disclosure, root: &Fr) -> Result<bool, PoseidonError> {
+    let commitments: Vec<Fr> = match disclosure
+        .commitments
+        .iter()
+        .map(|c| parse_fr("commitments", c))
+        .collect::<Result<_, _>>()
+    {
+        Ok(commitments) => commitments,
+        Err(_) => return Ok(false),
+    };
+    let commitments: [Fr; 6] = match commitments.try_into() {
+        Ok(commitments) => commitments,
+        Err(_) => return Ok(false),
+    };
+
+    // The committer's secret only matters for deriving salts, which are given.
+    let mut committer = FieldCommitter::new(Fr::from(0u64))?;
+    for (field, opening) in &disclosure.openings {
+        let column = match FIELD_NAMES.iter().position(|name| name == field) {
+            Some(column) => column,
+            None => return Ok(false),
+        };
+        let (value, salt) = match (
+            committer.encode_value(column, &opening.value),
+            parse_fr("salt", &opening.salt),
+        ) {
+            (Some(value), Ok(salt)) => (value?, salt),
+            _ => return Ok(false),
+        };
+        if committer.leaves.hash_pair(value, salt)? != commitments[column] {
+            return Ok(false);
+        }
+    }
+
+    let leaf = committer.leaf(&commitments)?;
+    Ok(parse_fr("leaf", &disclosure.proof.leaf).ok() == Some(leaf)
+        && disclosure.proof.leaf_index() == disclosure.row_index
+        && disclosure.proof.verify(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "transaction_id,date,customer_id,amount,type,description\n\
+        1,2024-05-20,42,10.5,debit,Coffee at Main St\n\
+        2,2024-05-21,42,-3.25,credit,Refund for order 1234\n\
+        3,2024-05-22,7,120,debit,Rent";
+    const SECRET: &str = "0badc0ffee";
+
+    fn parse_rules(rules_json: &str) -> Result<RedactionRules, String> {
+        check_rules(serde_json::from_str(rules_json).map_err(|e| e.to_string())?)
+    }
+
+    #[test]
+    fn test_redaction_rules() {
+        let rules = parse_rules(
+            r#"{"description": {"action": "drop"},
+                "customer_id": {"action": "tokenize"},
+                "date": {"action": "mask", "visible": 2},
+                "amount": {"action": "salted_hash"}}"#,
+        )
+        .unwrap();
+        let records = parse_records(CSV);
+        let mut committer = FieldCommitter::new(parse_fr("secret", SECRET).unwrap()).unwrap();
+
+        let first_commitments = committer.commitments(0, &records[0]).unwrap();
+        let first = committer
+            .redact(&records[0], &first_commitments, &rules)
+            .unwrap();
+        let second_commitments = committer.commitments(1, &records[1]).unwrap();
+        let second = committer
+            .redact(&records[1], &second_commitments, &rules)
+            .unwrap();
+
+        assert!(!first.contains_key("description"));
+        assert_eq!(first["date"], "********20");
+        assert_eq!(first["transaction_id"], 1);
+        assert_eq!(first["customer_id"], second["customer_id"]);
+        assert_eq!(first["amount"], fr_to_hex(&first_commitments[3]).as_str());
+
+        assert!(parse_rules(r#"{"iban": {"action": "drop"}}"#).is_err());
+
+        // Without rules, personal data is redacted anyway.
+        let defaults = committer
+            .redact(&records[0], &first_commitments, &RedactionRules::new())
+            .unwrap();
+        assert!(!defaults.contains_key("description"));
+        assert_eq!(defaults["customer_id"], first["customer_id"]);
+        assert_eq!(defaults["amount"], 10.5);
+
+        let keep = parse_rules(r#"{"description": {"action": "keep"}}"#).unwrap();
+        let kept = committer
+            .redact(&records[0], &first_commitments, &keep)
+            .unwrap();
+        assert_eq!(kept["description"], "Coffee at Main St");
+    }
+
+    #[test]
+    fn test_disclosure_verifies_only_opened_values() {
+        let secret = parse_fr("secret", SECRET).unwrap();
+        let output = redact_csv(CSV, &RedactionRules::new(), secret).unwrap();
+        let root = root_from_bytes(&output.merkle_root).unwrap();
+
+        let fields = ["amount".to_string(), "type".to_string()];
+        let disclosure = disclose(CSV, 1, &fields, secret).unwrap();
+        assert_eq!(disclosure.openings.len(), 2);
+        assert!(check_disclosure(&disclosure, &root).unwrap());
+
+        let mut forged = disclosure.clone();
+        forged.openings.get_mut("amount").unwrap().value = serde_json::json!(-30.0);
+        assert!(!check_disclosure(&forged, &root).unwrap());
+
+        let mut other_row = disclosure.clone();
+        other_row.row_index = 0;
+        assert!(!check_disclosure(&other_row, &root).unwrap());
+
+        let other = redact_csv(CSV, &RedactionRules::new(), Fr::from(7u64)).unwrap();
+        let other_root = root_from_bytes(&other.merkle_root).unwrap();
+        assert!(!check_disclosure(&disclosure, &other_root).unwrap());
+
+        assert!(disclose(CSV, 3, &fields, secret).is_err());
+    }
+}
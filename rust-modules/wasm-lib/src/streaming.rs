@@ -0,0 +1,266 @@
+use csv::{ReaderBuilder, StringRecord};
+use light_poseidon::PoseidonError;
+use serde::Serialize;
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::bindings::{to_js, JsStreamSummary};
+use crate::merkle::{fr_to_bytes, MerkleAccumulator};
+use crate::record::{LeafHasher, TransactionRecord};
+use crate::to_js_error;
+
+/// Longest row `CsvHasher` buffers while waiting for its end, e.g. when a
+/// quoted field is never closed.
+pub const MAX_RECORD_BYTES: usize = 1 << 20;
+
+#[derive(Serialize, Tsify)]
+pub struct StreamSummary {
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array")]
+    root: Vec<u8>,
+    depth: usize,
+    rows: usize,
+    skipped_rows: usize,
+    bytes: usize,
+}
+
+/// Incremental CSV hasher for uploads that are too large to hold in memory.
+///
+/// Feed the file with `update` in arbitrarily sized chunks and call
+/// `finalize` once at the end. Only the trailing incomplete row and the
+/// Merkle frontier are kept between chunks, and each byte is scanned once.
+#[wasm_bindgen]
+pub struct CsvHasher {
+    leaves: LeafHasher,
+    tree: MerkleAccumulator,
+    headers: Option<StringRecord>,
+    pending: String,
+    scanner: RecordScanner,
+    skipped_rows: usize,
+    bytes: usize,
+}
+
+#[wasm_bindgen]
+impl CsvHasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<CsvHasher, JsValue> {
+        Ok(CsvHasher {
+            leaves: LeafHasher::new().map_err(to_js_error)?,
+            tree: MerkleAccumulator::new().map_err(to_js_error)?,
+            headers: None,
+            pending: String::new(),
+            scanner: RecordScanner::default(),
+            skipped_rows: 0,
+            bytes: 0,
+        })
+    }
+
+    /// Appends a chunk of the file and hashes every row it completes.
+    /// Fails once a row grows past `MAX_RECORD_BYTES`.
+    pub fn update(&mut self, chunk: &str) -> Result<(), JsValue> {
+        self.append(chunk).map_err(to_js_error)
+    }
+
+    /// Number of rows hashed so far.
+    pub fn rows(&self) -> usize {
+        self.tree.leaf_count()
+    }
+
+    /// Hashes the remaining buffered row and returns the Merkle root together
+    /// with upload statistics.
+    pub fn finalize(self) -> Result<JsStreamSummary, JsValue> {
+        to_js(&self.summarize().map_err(to_js_error)?)
+    }
+}
+
+impl CsvHasher {
+    fn append(&mut self, chunk: &str) -> Result<(), String> {
+        self.bytes += chunk.len();
+        self.pending.push_str(chunk);
+
+        if let Some(end) = self.scanner.scan(&self.pending) {
+            let complete: String = self.pending.drain(..end).collect();
+            self.scanner.scanned -= end;
+            self.consume(&complete).map_err(|e| e.to_string())?;
+        }
+        if self.pending.len() > MAX_RECORD_BYTES {
+            return Err(format!("row longer than {} bytes", MAX_RECORD_BYTES));
+        }
+        Ok(())
+    }
+
+    fn summarize(mut self) -> Result<StreamSummary, PoseidonError> {
+        let rest = std::mem::take(&mut self.pending);
+        self.consume(&rest)?;
+
+        let rows = self.tree.leaf_count();
+        let (root, depth) = self.tree.finalize()?;
+
+        Ok(StreamSummary {
+            root: fr_to_bytes(&root),
+            depth,
+            rows,
+            skipped_rows: self.skipped_rows,
+            bytes: self.bytes,
+        })
+    }
+
+    /// Parses complete rows and pushes their leaves into the accumulator.
+    /// The first row seen is taken as the header.
+    fn consume(&mut self, rows: &str) -> Result<(), PoseidonError> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(rows.as_bytes());
+
+        for result in reader.records() {
+            let row = match result {
+                Ok(row) => row,
+                Err(_) => {
+                    self.skipped_rows += 1;
+                    continue;
+                }
+            };
+            let headers = match &self.headers {
+                Some(headers) => headers,
+                None => {
+                    self.headers = Some(row);
+                    continue;
+                }
+            };
+            match row.deserialize::<TransactionRecord>(Some(headers)) {
+                Ok(record) => {
+                    let leaf = self.leaves.leaf(&record)?;
+                    self.tree.push(leaf)?;
+                }
+                Err(_) => self.skipped_rows += 1,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the scanner is within a CSV row, following the quoting rules of the
+/// `csv` crate: a quote only opens a quoted field at the start of a field,
+/// and `""` inside one is an escaped quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Start,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+/// Finds row ends in the pending buffer, keeping its state between chunks so
+/// that every byte is looked at once.
+#[derive(Debug)]
+struct RecordScanner {
+    field: Field,
+    /// Length of the buffer prefix already scanned.
+    scanned: usize,
+}
+
+impl Default for RecordScanner {
+    fn default() -> Self {
+        RecordScanner {
+            field: Field::Start,
+            scanned: 0,
+        }
+    }
+}
+
+impl RecordScanner {
+    /// Scans the unscanned tail of `buffer` and returns the offset just past
+    /// the last newline that ends a row, so that rows are never split across
+    /// two parses.
+    fn scan(&mut self, buffer: &str) -> Option<usize> {
+        let mut boundary = None;
+        for (i, byte) in buffer.bytes().enumerate().skip(self.scanned) {
+            self.field = match (self.field, byte) {
+                (Field::Quoted, b'"') => Field::QuoteInQuoted,
+                (Field::Quoted, _) => Field::Quoted,
+                (Field::QuoteInQuoted, b'"') | (Field::Start, b'"') => Field::Quoted,
+                (_, b',') => Field::Start,
+                (_, b'\n') => {
+                    boundary = Some(i + 1);
+                    Field::Start
+                }
+                _ => Field::Unquoted,
+            };
+        }
+        self.scanned = buffer.len();
+        boundary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "transaction_id,date,customer_id,amount,type,description\n\
+        1,2024-05-20,42,10.5,debit,\"Coffee, large\"\n\
+        2,2024-05-21,42,-3.25,credit,Refund\n\
+        3,2024-05-22,7,120,debit,\"Multi\nline\"\n\
+        not,a,valid,row,at,all\n\
+        4,2024-05-23,7,0.99,debit,Gum";
+
+    fn summary(chunks: &[&str]) -> StreamSummary {
+        let mut hasher = CsvHasher::new().unwrap();
+        for chunk in chunks {
+            hasher.update(chunk).unwrap();
+        }
+        hasher.summarize().unwrap()
+    }
+
+    #[test]
+    fn test_chunking_does_not_change_root() {
+        let whole = summary(&[CSV]);
+        let (a, b) = CSV.split_at(57);
+        let (b, c) = b.split_at(40);
+        let split = summary(&[a, b, c]);
+
+        assert_eq!(whole.root, split.root);
+        assert_eq!(whole.rows, 4);
+        assert_eq!(whole.skipped_rows, 1);
+        assert_eq!(whole.depth, 2);
+        assert_eq!(whole.bytes, CSV.len());
+
+        let bytes: Vec<String> = CSV.chars().map(String::from).collect();
+        let bytes: Vec<&str> = bytes.iter().map(String::as_str).collect();
+        assert_eq!(summary(&bytes).root, whole.root);
+    }
+
+    #[test]
+    fn test_root_matches_hash_csv() {
+        let csv = format!("{}\n5,2024-05-24,9,1,debit,5\" screen", CSV);
+        assert_eq!(
+            summary(&[&csv]).root,
+            crate::hash_csv(&csv).unwrap().merkle_root
+        );
+    }
+
+    #[test]
+    fn test_stray_quote_does_not_hold_back_rows() {
+        let mut hasher = CsvHasher::new().unwrap();
+        hasher
+            .update("transaction_id,date,customer_id,amount,type,description\n")
+            .unwrap();
+        hasher
+            .update("1,2024-05-20,42,10.5,debit,5\" screen\n")
+            .unwrap();
+        hasher
+            .update("2,2024-05-21,42,-3.25,credit,\"a \"\"b\"\"\"\n")
+            .unwrap();
+        assert_eq!(hasher.rows(), 2);
+        assert_eq!(hasher.pending, "");
+    }
+
+    #[test]
+    fn test_rejects_overlong_rows() {
+        let mut hasher = CsvHasher::new().unwrap();
+        hasher.append("transaction_id,date\n1,\"").unwrap();
+        // The open quoted field fills the buffer right up to the limit.
+        hasher.append(&"x".repeat(MAX_RECORD_BYTES - 3)).unwrap();
+        assert_eq!(hasher.scanner.field, Field::Quoted);
+        assert!(hasher.append("x").is_err());
+    }
+}
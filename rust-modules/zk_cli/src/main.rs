@@ -0,0 +1,353 @@
+//! `zk`: key setup, proving, verification and the wasm-lib data processing
+//! from the command line.
+//!
+//! Every input named `-` (the default) is read from stdin and every output
+//! goes to stdout unless `--output` is given, so the commands can be piped
+//! into each other. Keys are stored uncompressed under the file names the
+//! Fluence service looks for.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ark_bn254::Bn254;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::thread_rng;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use serde_json::json;
+use zk_snarks::{
+    parse_data, parse_fr, OwnershipCircuit, ProofEnvelope, MEMBERSHIP_CIRCUIT_ID,
+    OWNERSHIP_CIRCUIT_ID,
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    name = "zk",
+    version,
+    about = "Membership and ownership proofs and upload processing"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Write the result here instead of stdout.
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a proving and a verifying key for one circuit and Merkle
+    /// depth.
+    Setup {
+        #[arg(long)]
+        depth: usize,
+        #[arg(long, value_enum, default_value_t = Circuit::Membership)]
+        circuit: Circuit,
+        /// Directory the `.pk` and `.vk` files are written to.
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Prove membership of a leaf and print the proof envelope as JSON.
+    Prove {
+        /// Little-endian hex leaf.
+        #[arg(long)]
+        leaf: String,
+        /// JSON with `siblings` and `path_bits`, e.g. one of the
+        /// `merkle_proofs` printed by `hash-csv`.
+        #[arg(long, default_value = "-")]
+        path: PathBuf,
+        #[arg(long)]
+        pk: PathBuf,
+        /// Recorded as the envelope's `prover`.
+        #[arg(long, default_value = "")]
+        prover: String,
+    },
+    /// Prove ownership of an identity commitment in one context and print
+    /// the proof envelope as JSON.
+    ProveOwnership {
+        /// Little-endian hex secret whose commitment is the leaf.
+        #[arg(long)]
+        secret: String,
+        /// JSON with `siblings` and `path_bits` of the commitment.
+        #[arg(long, default_value = "-")]
+        path: PathBuf,
+        /// Little-endian hex context, e.g. one opened in `OwnershipRegistry`.
+        #[arg(long)]
+        external_nullifier: String,
+        /// Little-endian hex value the proof is bound to.
+        #[arg(long, default_value = "00")]
+        signal: String,
+        #[arg(long)]
+        pk: PathBuf,
+        /// Recorded as the envelope's `prover`.
+        #[arg(long, default_value = "")]
+        prover: String,
+    },
+    /// Verify a proof envelope; exits with status 1 if it does not verify.
+    Verify {
+        #[arg(long)]
+        vk: PathBuf,
+        #[arg(default_value = "-")]
+        envelope: PathBuf,
+    },
+    /// Print a Solidity verifier contract for a verifying key.
+    ExportSolidity {
+        #[arg(long)]
+        vk: PathBuf,
+    },
+    /// Print the `verifyProof` arguments for a proof envelope.
+    ExportCalldata {
+        #[arg(default_value = "-")]
+        envelope: PathBuf,
+    },
+    /// Parse, hash and commit to a transaction CSV.
+    HashCsv {
+        #[arg(default_value = "-")]
+        input: PathBuf,
+    },
+    /// Parse HTTP access log lines into JSON.
+    ParseLogs {
+        #[arg(default_value = "-")]
+        input: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Circuit {
+    Membership,
+    Ownership,
+}
+
+impl Circuit {
+    fn id(self) -> &'static str {
+        match self {
+            Circuit::Membership => MEMBERSHIP_CIRCUIT_ID,
+            Circuit::Ownership => OWNERSHIP_CIRCUIT_ID,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MerklePath {
+    siblings: Vec<String>,
+    path_bits: Vec<bool>,
+}
+
+fn read_input(path: &Path) -> CliResult<Vec<u8>> {
+    if path == Path::new("-") {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    fs::read(path).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+fn read_text(path: &Path) -> CliResult<String> {
+    Ok(String::from_utf8(read_input(path)?)?)
+}
+
+fn read_envelope(path: &Path) -> CliResult<ProofEnvelope> {
+    Ok(ProofEnvelope::from_json(&read_text(path)?)?)
+}
+
+fn read_vk(path: &Path) -> CliResult<VerifyingKey<Bn254>> {
+    Ok(VerifyingKey::deserialize_uncompressed(
+        &read_input(path)?[..],
+    )?)
+}
+
+fn write_key<T: CanonicalSerialize>(path: &Path, key: &T) -> CliResult<()> {
+    let mut bytes = Vec::new();
+    key.serialize_uncompressed(&mut bytes)?;
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// Runs `command` and returns its output and whether it succeeded.
+fn run(command: Command) -> CliResult<(String, bool)> {
+    let output = match command {
+        Command::Setup {
+            depth,
+            circuit,
+            dir,
+        } => {
+            let pk = match circuit {
+                Circuit::Membership => zk_snarks::generate_proving_key(depth, &mut thread_rng())?,
+                Circuit::Ownership => zk_snarks::generate_ownership_key(depth, &mut thread_rng())?,
+            };
+            let stem = dir.join(format!("{}_depth{}", circuit.id(), depth));
+            let (pk_path, vk_path) = (stem.with_extension("pk"), stem.with_extension("vk"));
+            write_key(&pk_path, &pk)?;
+            write_key(&vk_path, &pk.vk)?;
+            json!({
+                "pk": pk_path,
+                "vk": vk_path,
+                "vk_id": zk_snarks::vk_id(&pk.vk)?,
+            })
+            .to_string()
+        }
+        Command::Prove {
+            leaf,
+            path,
+            pk,
+            prover,
+        } => {
+            let path: MerklePath = serde_json::from_str(&read_text(&path)?)?;
+            let data = parse_data(&leaf, &path.siblings, &path.path_bits)?;
+            let pk = ProvingKey::<Bn254>::deserialize_uncompressed(&read_input(&pk)?[..])?;
+            zk_snarks::prove(&pk, &data, &mut thread_rng(), &prover)?.to_json()?
+        }
+        Command::ProveOwnership {
+            secret,
+            path,
+            external_nullifier,
+            signal,
+            pk,
+            prover,
+        } => {
+            let path: MerklePath = serde_json::from_str(&read_text(&path)?)?;
+            let siblings = path
+                .siblings
+                .iter()
+                .map(|sibling| parse_fr("siblings", sibling))
+                .collect::<Result<Vec<_>, _>>()?;
+            let circuit = OwnershipCircuit::new(
+                parse_fr("secret", &secret)?,
+                &siblings,
+                &path.path_bits,
+                parse_fr("external_nullifier", &external_nullifier)?,
+                parse_fr("signal", &signal)?,
+            )?;
+            let pk = ProvingKey::<Bn254>::deserialize_uncompressed(&read_input(&pk)?[..])?;
+            zk_snarks::prove_ownership(&pk, circuit, &mut thread_rng(), &prover)?.to_json()?
+        }
+        Command::Verify { vk, envelope } => {
+            let valid = read_envelope(&envelope)?.verify(&read_vk(&vk)?)?;
+            return Ok((json!({ "valid": valid }).to_string(), valid));
+        }
+        Command::ExportSolidity { vk } => zk_snarks::solidity::verifier_contract(&read_vk(&vk)?)?,
+        Command::ExportCalldata { envelope } => {
+            let envelope = read_envelope(&envelope)?;
+            let proof = envelope.decode_proof()?;
+            zk_snarks::solidity::calldata(&proof, &envelope.decode_public_inputs()?)
+        }
+        Command::HashCsv { input } => {
+            serde_json::to_string(&wasm_data_processor::hash_csv(&read_text(&input)?)?)?
+        }
+        Command::ParseLogs { input } => {
+            serde_json::to_string(&wasm_data_processor::parse_logs(&read_text(&input)?))?
+        }
+    };
+    Ok((output, true))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = run(cli.command).and_then(|(output, ok)| {
+        match &cli.output {
+            Some(path) => fs::write(path, output + "\n")?,
+            None => writeln!(io::stdout(), "{}", output)?,
+        }
+        Ok(ok)
+    });
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_prove_verify() {
+        let dir = std::env::temp_dir().join(format!("zk_cli_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (setup, _) = run(Command::Setup {
+            depth: 1,
+            circuit: Circuit::Membership,
+            dir: dir.clone(),
+        })
+        .unwrap();
+        let setup: serde_json::Value = serde_json::from_str(&setup).unwrap();
+        let vk = PathBuf::from(setup["vk"].as_str().unwrap());
+
+        let path = dir.join("path.json");
+        fs::write(&path, r#"{"siblings": ["0b"], "path_bits": [true]}"#).unwrap();
+        let (envelope, _) = run(Command::Prove {
+            leaf: "07".to_string(),
+            path,
+            pk: PathBuf::from(setup["pk"].as_str().unwrap()),
+            prover: "test".to_string(),
+        })
+        .unwrap();
+        let envelope_path = dir.join("envelope.json");
+        fs::write(&envelope_path, &envelope).unwrap();
+
+        let (verdict, valid) = run(Command::Verify {
+            vk: vk.clone(),
+            envelope: envelope_path.clone(),
+        })
+        .unwrap();
+        assert!(valid, "{}", verdict);
+        let (calldata, _) = run(Command::ExportCalldata {
+            envelope: envelope_path,
+        })
+        .unwrap();
+        assert_eq!(calldata.matches("0x").count(), 9);
+        let (contract, _) = run(Command::ExportSolidity { vk }).unwrap();
+        assert!(contract.contains("contract Verifier"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ownership_setup_prove_verify() {
+        let dir = std::env::temp_dir().join(format!("zk_cli_ownership_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (setup, _) = run(Command::Setup {
+            depth: 1,
+            circuit: Circuit::Ownership,
+            dir: dir.clone(),
+        })
+        .unwrap();
+        let setup: serde_json::Value = serde_json::from_str(&setup).unwrap();
+        let vk = PathBuf::from(setup["vk"].as_str().unwrap());
+        assert!(vk.ends_with(format!("{}_depth1.vk", OWNERSHIP_CIRCUIT_ID)));
+
+        let path = dir.join("path.json");
+        fs::write(&path, r#"{"siblings": ["0b"], "path_bits": [false]}"#).unwrap();
+        let (envelope, _) = run(Command::ProveOwnership {
+            secret: "05".to_string(),
+            path,
+            external_nullifier: "07".to_string(),
+            signal: "2a".to_string(),
+            pk: PathBuf::from(setup["pk"].as_str().unwrap()),
+            prover: "test".to_string(),
+        })
+        .unwrap();
+        let envelope_path = dir.join("envelope.json");
+        fs::write(&envelope_path, &envelope).unwrap();
+
+        let (verdict, valid) = run(Command::Verify {
+            vk: vk.clone(),
+            envelope: envelope_path,
+        })
+        .unwrap();
+        assert!(valid, "{}", verdict);
+        let (contract, _) = run(Command::ExportSolidity { vk }).unwrap();
+        assert!(contract.contains("uint[4] memory input_"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,480 @@
+//! Proofs of aggregate statistics over a committed transaction upload.
+//!
+//! The public inputs are the upload's record root followed by its count,
+//! sum, smallest and largest amount, earliest and latest date and the totals
+//! of a fixed list of types. The circuit recomputes every one of them from
+//! all committed records, so none can be left out or counted twice.
+//!
+//! Dates are committed as `H(date)`; the circuit opens them from their bytes,
+//! which must form an ISO `YYYY-MM-DD` string, so that they compare in
+//! calendar order.
+
+use std::sync::Arc;
+
+use ark_bn254::Fr as ArkFr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use tracing::{debug, instrument};
+
+use crate::poseidon::PoseidonGadget;
+use crate::range::{
+    enforce_range, records_root, signed_fr, RecordFields, AMOUNT_BITS, AMOUNT_FIELD, TYPE_FIELD,
+};
+use crate::ZkError;
+
+/// Circuit id of `AggregateCircuit` in proof envelopes and key files.
+pub const AGGREGATE_CIRCUIT_ID: &str = "upload_aggregates";
+
+/// Length of an ISO `YYYY-MM-DD` date.
+pub const DATE_LEN: usize = 10;
+const DATE_FIELD: usize = 1;
+const DATE_BITS: usize = 8 * DATE_LEN;
+/// Positions of the `-` separators; every other byte is a digit.
+const DATE_SEPARATORS: [usize; 2] = [4, 7];
+
+fn invalid(field: &'static str, reason: String) -> ZkError {
+    ZkError::InvalidInput { field, reason }
+}
+
+/// The bytes of a date of the right length, whatever they are.
+fn date_chunk(date: &str) -> Option<[u8; DATE_LEN]> {
+    date.as_bytes().try_into().ok()
+}
+
+fn date_bytes(date: &str) -> Result<[u8; DATE_LEN], ZkError> {
+    let iso = |bytes: &[u8; DATE_LEN]| {
+        bytes.iter().enumerate().all(|(i, byte)| {
+            if DATE_SEPARATORS.contains(&i) {
+                *byte == b'-'
+            } else {
+                byte.is_ascii_digit()
+            }
+        })
+    };
+    date_chunk(date)
+        .filter(iso)
+        .ok_or_else(|| invalid("date", format!("{:?} is not YYYY-MM-DD", date)))
+}
+
+/// `H(date)` as wasm-lib's `LeafHasher::hash_str` computes it for a string
+/// of one chunk: `Poseidon(length, little-endian bytes)`.
+pub fn date_hash(date: &str) -> Result<ArkFr, ZkError> {
+    let chunk = ArkFr::from_le_bytes_mod_order(&date_bytes(date)?);
+    Ok(Poseidon::<ArkFr>::new_circom(2)?.hash(&[ArkFr::from(DATE_LEN as u64), chunk])?)
+}
+
+/// The date's bytes read as a big-endian number, which orders ISO dates
+/// chronologically.
+pub fn date_key(date: &str) -> Result<u128, ZkError> {
+    Ok(date_bytes(date)?
+        .iter()
+        .fold(0, |key, byte| key << 8 | u128::from(*byte)))
+}
+
+/// Inverse of `signed_fr` for committed amounts.
+fn signed_amount(value: ArkFr) -> Option<i64> {
+    let small = |value: ArkFr| {
+        let bigint = value.into_bigint();
+        (bigint.num_bits() <= 63).then(|| bigint.as_ref()[0] as i64)
+    };
+    small(value).or_else(|| small(-value).map(|magnitude| -magnitude))
+}
+
+/// A committed record and the date behind its `H(date)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatedRecord {
+    pub fields: RecordFields,
+    pub date: String,
+}
+
+impl DatedRecord {
+    /// Checks that `date` is the committed one and the amount a 64-bit
+    /// fixed-point value.
+    pub fn new(fields: RecordFields, date: &str) -> Result<Self, ZkError> {
+        if date_hash(date)? != fields[DATE_FIELD] {
+            return Err(invalid(
+                "date",
+                format!("{:?} is not the committed date", date),
+            ));
+        }
+        if signed_amount(fields[AMOUNT_FIELD]).is_none() {
+            return Err(invalid("amount", "not a fixed-point amount".to_string()));
+        }
+        Ok(DatedRecord {
+            fields,
+            date: date.to_string(),
+        })
+    }
+
+    fn amount(&self) -> i64 {
+        signed_amount(self.fields[AMOUNT_FIELD]).unwrap_or_default()
+    }
+}
+
+/// Statistics of an upload, amounts in cents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aggregates {
+    pub count: u64,
+    pub sum: i64,
+    pub min: i64,
+    pub max: i64,
+    pub date_min: String,
+    pub date_max: String,
+    /// `(H(type), total)` for each type the circuit was built for.
+    pub type_totals: Vec<(ArkFr, i64)>,
+}
+
+impl Aggregates {
+    /// Computes the statistics of a non-empty upload.
+    pub fn compute(records: &[DatedRecord], type_hashes: &[ArkFr]) -> Result<Self, ZkError> {
+        let overflow = || invalid("records", "sum of amounts overflows".to_string());
+        let total = |of_type: &dyn Fn(&DatedRecord) -> bool| {
+            records
+                .iter()
+                .filter(|record| of_type(record))
+                .try_fold(0i64, |sum, record| sum.checked_add(record.amount()))
+                .ok_or_else(overflow)
+        };
+        let amounts = records.iter().map(DatedRecord::amount);
+        let dates = records.iter().map(|record| &record.date);
+        let empty = || invalid("records", "nothing to aggregate".to_string());
+
+        Ok(Aggregates {
+            count: records.len() as u64,
+            sum: total(&|_| true)?,
+            min: amounts.clone().min().ok_or_else(empty)?,
+            max: amounts.max().ok_or_else(empty)?,
+            date_min: dates.clone().min().ok_or_else(empty)?.clone(),
+            date_max: dates.max().ok_or_else(empty)?.clone(),
+            type_totals: type_hashes
+                .iter()
+                .map(|type_hash| {
+                    let of_type = |record: &DatedRecord| record.fields[TYPE_FIELD] == *type_hash;
+                    Ok((*type_hash, total(&of_type)?))
+                })
+                .collect::<Result<_, ZkError>>()?,
+        })
+    }
+
+    /// Public inputs in circuit order: the root, then the statistics.
+    pub fn public_inputs(&self, root: ArkFr) -> Result<Vec<ArkFr>, ZkError> {
+        let mut inputs = vec![
+            root,
+            ArkFr::from(self.count),
+            signed_fr(self.sum),
+            signed_fr(self.min),
+            signed_fr(self.max),
+            ArkFr::from(date_key(&self.date_min)?),
+            ArkFr::from(date_key(&self.date_max)?),
+        ];
+        for (type_hash, total) in &self.type_totals {
+            inputs.extend([*type_hash, signed_fr(*total)]);
+        }
+        Ok(inputs)
+    }
+}
+
+/// Aggregate circuit for uploads of up to `2^depth` records, totalling
+/// `types` types.
+#[derive(Clone)]
+pub struct AggregateCircuit {
+    records: Vec<DatedRecord>,
+    depth: usize,
+    types: usize,
+    inputs: Vec<ArkFr>,
+    record_hash: Arc<PoseidonGadget>,
+    pair_hash: Arc<PoseidonGadget>,
+}
+
+impl AggregateCircuit {
+    /// Circuit proving the statistics of `records`, committed under a tree
+    /// of `depth` levels, with a total for each of `type_hashes`.
+    pub fn new(
+        records: &[DatedRecord],
+        depth: usize,
+        type_hashes: &[ArkFr],
+    ) -> Result<Self, ZkError> {
+        let fields: Vec<RecordFields> = records.iter().map(|record| record.fields).collect();
+        let root = records_root(&fields, depth)?;
+        let inputs = Aggregates::compute(records, type_hashes)?.public_inputs(root)?;
+        Self::with_inputs(records, depth, type_hashes.len(), inputs)
+    }
+
+    /// A circuit of the given shape with an empty upload, to run the setup.
+    pub fn blank(depth: usize, types: usize) -> Result<Self, ZkError> {
+        Self::with_inputs(&[], depth, types, vec![ArkFr::from(0u64); 7 + 2 * types])
+    }
+
+    fn with_inputs(
+        records: &[DatedRecord],
+        depth: usize,
+        types: usize,
+        inputs: Vec<ArkFr>,
+    ) -> Result<Self, ZkError> {
+        Ok(AggregateCircuit {
+            records: records.to_vec(),
+            depth,
+            types,
+            inputs,
+            record_hash: Arc::new(PoseidonGadget::new(6)?),
+            pair_hash: Arc::new(PoseidonGadget::new(2)?),
+        })
+    }
+
+    pub fn public_inputs(&self) -> &[ArkFr] {
+        &self.inputs
+    }
+}
+
+impl ConstraintSynthesizer<ArkFr> for AggregateCircuit {
+    // Witness values are private: they may only be emitted at trace level.
+    #[instrument(name = "synthesis", skip_all, fields(depth = self.depth, types = self.types))]
+    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|value| FpVar::<ArkFr>::new_input(cs.clone(), || Ok(*value)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if inputs.len() != 7 + 2 * self.types {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let (root, count, sum, min, max) =
+            (&inputs[0], &inputs[1], &inputs[2], &inputs[3], &inputs[4]);
+        let (date_min, date_max) = (&inputs[5], &inputs[6]);
+        let type_hashes: Vec<&FpVar<ArkFr>> = inputs[7..].iter().step_by(2).collect();
+
+        let one = FpVar::<ArkFr>::one();
+        let [separator, digit_min, digit_max] =
+            [b'-', b'0', b'9'].map(|byte| FpVar::<ArkFr>::constant(ArkFr::from(byte)));
+        let mut leaves = Vec::with_capacity(1 << self.depth);
+        let (mut counted, mut summed) = (FpVar::<ArkFr>::zero(), FpVar::<ArkFr>::zero());
+        let mut totals = vec![FpVar::<ArkFr>::zero(); self.types];
+        // Products of the differences to each bound: zero iff some record
+        // attains it.
+        let mut attained = vec![one.clone(); 4];
+
+        for slot in 0..1 << self.depth {
+            let record = self.records.get(slot);
+            let present = Boolean::new_witness(cs.clone(), || Ok(record.is_some()))?;
+            let fields = (0..6)
+                .map(|i| {
+                    FpVar::new_witness(cs.clone(), || {
+                        Ok(record.map_or(ArkFr::from(0u64), |record| record.fields[i]))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let leaf = self.record_hash.hash(&fields)?;
+            leaves.push(present.select(&leaf, &FpVar::zero())?);
+
+            // The date's bytes, hashed as wasm-lib does and read big-endian.
+            // Each must be a digit or a separator, as `date_bytes` checks.
+            let bytes = record
+                .and_then(|record| date_chunk(&record.date))
+                .unwrap_or_default();
+            let (mut chunk, mut date) = (FpVar::<ArkFr>::zero(), FpVar::<ArkFr>::zero());
+            for (i, byte) in bytes.iter().enumerate() {
+                let mut byte_var = FpVar::<ArkFr>::zero();
+                for bit in 0..8 {
+                    let bit_var = Boolean::new_witness(cs.clone(), || Ok(byte >> bit & 1 == 1))?;
+                    byte_var += FpVar::from(bit_var) * ArkFr::from(1u64 << bit);
+                }
+                if DATE_SEPARATORS.contains(&i) {
+                    byte_var.conditional_enforce_equal(&separator, &present)?;
+                } else {
+                    let digit = present.select(&byte_var, &digit_min)?;
+                    enforce_range(&digit, &digit_min, &digit_max, 4)?;
+                }
+                chunk += &byte_var * ArkFr::from(256u64).pow([i as u64]);
+                date = date * ArkFr::from(256u64) + byte_var;
+            }
+            let date_hash = self
+                .pair_hash
+                .hash(&[FpVar::constant(ArkFr::from(DATE_LEN as u64)), chunk])?;
+            date_hash.conditional_enforce_equal(&fields[DATE_FIELD], &present)?;
+
+            let amount = &fields[AMOUNT_FIELD];
+            enforce_range(&present.select(amount, min)?, min, max, AMOUNT_BITS)?;
+            enforce_range(
+                &present.select(&date, date_min)?,
+                date_min,
+                date_max,
+                DATE_BITS,
+            )?;
+            for (product, difference) in attained.iter_mut().zip([
+                amount - min,
+                max - amount,
+                &date - date_min,
+                date_max - &date,
+            ]) {
+                *product *= present.select(&difference, &one)?;
+            }
+
+            counted += FpVar::from(present.clone());
+            summed += present.select(amount, &FpVar::zero())?;
+            for (total, type_hash) in totals.iter_mut().zip(&type_hashes) {
+                let of_type = present.and(&fields[TYPE_FIELD].is_eq(type_hash)?)?;
+                *total += of_type.select(amount, &FpVar::zero())?;
+            }
+        }
+
+        counted.enforce_equal(count)?;
+        summed.enforce_equal(sum)?;
+        for product in &attained {
+            product.enforce_equal(&FpVar::zero())?;
+        }
+        for (total, expected) in totals.iter().zip(inputs[8..].iter().step_by(2)) {
+            total.enforce_equal(expected)?;
+        }
+
+        while leaves.len() > 1 {
+            leaves = leaves
+                .chunks(2)
+                .map(|pair| self.pair_hash.hash(pair))
+                .collect::<Result<_, _>>()?;
+        }
+        leaves[0].enforce_equal(root)?;
+
+        debug!(constraints = cs.num_constraints(), "synthesized");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::fixed_amount;
+    use ark_bn254::Bn254;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+
+    const DEBIT: u64 = 1;
+    const CREDIT: u64 = 2;
+
+    fn records() -> Vec<DatedRecord> {
+        [
+            (1, "2024-05-21", 12.5, DEBIT),
+            (2, "2024-04-30", 80.0, CREDIT),
+            (3, "2024-05-02", -4.25, DEBIT),
+        ]
+        .iter()
+        .map(|&(id, date, amount, type_hash)| {
+            let fields = [
+                ArkFr::from(id),
+                date_hash(date).unwrap(),
+                ArkFr::from(7u64),
+                signed_fr(fixed_amount(amount)),
+                ArkFr::from(type_hash),
+                ArkFr::from(200 + id),
+            ];
+            DatedRecord::new(fields, date).unwrap()
+        })
+        .collect()
+    }
+
+    fn circuit() -> AggregateCircuit {
+        let types = [ArkFr::from(DEBIT), ArkFr::from(CREDIT)];
+        AggregateCircuit::new(&records(), 2, &types).unwrap()
+    }
+
+    fn is_satisfied(circuit: AggregateCircuit) -> bool {
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_computes_aggregates() {
+        let types = [ArkFr::from(DEBIT), ArkFr::from(CREDIT)];
+        let aggregates = Aggregates::compute(&records(), &types).unwrap();
+        assert_eq!(
+            aggregates,
+            Aggregates {
+                count: 3,
+                sum: 8825,
+                min: -425,
+                max: 8000,
+                date_min: "2024-04-30".to_string(),
+                date_max: "2024-05-21".to_string(),
+                type_totals: vec![(types[0], 825), (types[1], 8000)],
+            }
+        );
+        assert!(date_key("2024-04-30").unwrap() < date_key("2024-05-02").unwrap());
+        assert_eq!(Aggregates::compute(&[], &types).unwrap_err().code(), 14);
+    }
+
+    #[test]
+    fn test_rejects_wrong_aggregates() {
+        assert!(is_satisfied(circuit()));
+
+        let max_below_record = signed_fr(7999);
+        let max_above_records = signed_fr(8001);
+        let date_min_before_records = ArkFr::from(date_key("2024-04-29").unwrap());
+        for (index, value) in [
+            (1, ArkFr::from(2u64)),
+            (2, signed_fr(8824)),
+            (4, max_below_record),
+            (4, max_above_records),
+            (5, date_min_before_records),
+            (8, signed_fr(826)),
+        ] {
+            let mut circuit = circuit();
+            circuit.inputs[index] = value;
+            assert!(!is_satisfied(circuit), "input {} accepted", index);
+        }
+    }
+
+    #[test]
+    fn test_rejects_uncommitted_date() {
+        let record = &records()[0];
+        let err = DatedRecord::new(record.fields, "2024-05-22").unwrap_err();
+        assert_eq!(err.code(), 14);
+        assert_eq!(date_hash("2024-5-22").unwrap_err().code(), 14);
+    }
+
+    #[test]
+    fn test_rejects_non_iso_date() {
+        let date = "05/21/2024";
+        assert_eq!(date_hash(date).unwrap_err().code(), 14);
+        assert_eq!(date_key("2024-05-2x").unwrap_err().code(), 14);
+
+        // Committed anyway, the date would sort before every ISO date.
+        let mut records = records();
+        let chunk = ArkFr::from_le_bytes_mod_order(date.as_bytes());
+        let mut hasher = Poseidon::<ArkFr>::new_circom(2).unwrap();
+        records[1].fields[DATE_FIELD] = hasher.hash(&[ArkFr::from(10u64), chunk]).unwrap();
+        records[1].date = date.to_string();
+        let fields: Vec<RecordFields> = records.iter().map(|record| record.fields).collect();
+        let mut inputs = circuit().inputs;
+        inputs[0] = records_root(&fields, 2).unwrap();
+        inputs[5] = ArkFr::from(
+            date.bytes()
+                .fold(0u128, |key, byte| key << 8 | u128::from(byte)),
+        );
+        let circuit = AggregateCircuit::with_inputs(&records, 2, 2, inputs).unwrap();
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_groth16_round_trip() {
+        let mut rng = test_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            AggregateCircuit::blank(2, 2).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+        let circuit = circuit();
+        let inputs = circuit.public_inputs().to_vec();
+        let proof =
+            Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, &mut rng).unwrap();
+
+        let pvk = ark_groth16::prepare_verifying_key(&pk.vk);
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &inputs).unwrap());
+        let mut other = inputs;
+        other[1] = ArkFr::from(4u64);
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other).unwrap());
+    }
+}
@@ -0,0 +1,257 @@
+//! Ed25519 signatures over proof envelopes, so that each answer of a fan-out
+//! can be traced to the worker that produced it, and the k-of-n policy for
+//! accepting such answers.
+
+use std::collections::HashMap;
+
+use ark_bn254::Bn254;
+use ark_groth16::VerifyingKey;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey as PublicKey};
+use tracing::debug;
+
+use crate::{EnvelopeSignature, ProofEnvelope, ZkError};
+
+/// `EnvelopeSignature::algorithm` of the signatures made here.
+pub const ED25519: &str = "ed25519";
+
+/// Signs `envelope` with `key`, replacing any earlier signature.
+pub fn sign_envelope(envelope: &mut ProofEnvelope, key: &SigningKey) -> Result<(), ZkError> {
+    let signature = key.sign(&envelope.signed_bytes()?);
+    envelope.signature = Some(EnvelopeSignature {
+        algorithm: ED25519.to_string(),
+        public_key: key.verifying_key().to_bytes().to_vec(),
+        signature: signature.to_bytes().to_vec(),
+    });
+    Ok(())
+}
+
+/// Checks the envelope's signature and returns the key that made it. Whether
+/// that key is trusted is up to the caller.
+pub fn verify_signature(envelope: &ProofEnvelope) -> Result<PublicKey, ZkError> {
+    let signature = envelope
+        .signature
+        .as_ref()
+        .ok_or_else(|| ZkError::Signature("the envelope is unsigned".to_string()))?;
+    if signature.algorithm != ED25519 {
+        return Err(ZkError::UnsupportedEnvelope {
+            field: "signature.algorithm",
+            value: signature.algorithm.clone(),
+        });
+    }
+
+    let public_key = <[u8; 32]>::try_from(signature.public_key.as_slice()).map_err(|_| {
+        ZkError::Signature(format!("{}-byte public key", signature.public_key.len()))
+    })?;
+    let public_key =
+        PublicKey::from_bytes(&public_key).map_err(|e| ZkError::Signature(e.to_string()))?;
+    let signature = Signature::from_slice(&signature.signature)
+        .map_err(|e| ZkError::Signature(e.to_string()))?;
+    public_key
+        .verify_strict(&envelope.signed_bytes()?, &signature)
+        .map_err(|_| ZkError::Signature("does not match the envelope".to_string()))?;
+    Ok(public_key)
+}
+
+/// Acceptance policy for the answers of several workers: an answer is
+/// accepted once `threshold` distinct trusted signers have sent valid proofs
+/// of the same statement, i.e. the same circuit, verifying key and public
+/// inputs. The proofs themselves differ, since proving is randomized.
+#[derive(Debug, Clone)]
+pub struct Quorum {
+    threshold: usize,
+    /// Keys whose answers count.
+    trusted: Vec<PublicKey>,
+}
+
+impl Quorum {
+    /// Requires `threshold` of the `trusted` keys, which must not be empty.
+    pub fn new(threshold: usize, trusted: Vec<PublicKey>) -> Result<Self, ZkError> {
+        if threshold == 0 {
+            return Err(invalid("the threshold must be at least 1".to_string()));
+        }
+        if trusted.len() < threshold {
+            return Err(invalid(format!(
+                "a threshold of {} needs at least as many trusted signers, got {}",
+                threshold,
+                trusted.len()
+            )));
+        }
+        Ok(Quorum { threshold, trusted })
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the answer completing the first statement to reach the
+    /// threshold. Unsigned, untrusted and invalid answers are skipped.
+    pub fn accept<'a>(
+        &self,
+        answers: &'a [ProofEnvelope],
+        vk: &VerifyingKey<Bn254>,
+    ) -> Result<&'a ProofEnvelope, ZkError> {
+        let mut signers: HashMap<(&str, &str, &[String]), Vec<PublicKey>> = HashMap::new();
+        let mut agreeing = 0;
+
+        for answer in answers {
+            let signer = match self.signer(answer, vk) {
+                Ok(Some(signer)) => signer,
+                Ok(None) => {
+                    debug!(prover = %answer.prover, "skipping untrusted or invalid answer");
+                    continue;
+                }
+                Err(e) => {
+                    debug!(prover = %answer.prover, error = %e, "skipping malformed answer");
+                    continue;
+                }
+            };
+
+            let statement = (
+                answer.circuit_id.as_str(),
+                answer.vk_id.as_str(),
+                answer.public_inputs.as_slice(),
+            );
+            let group = signers.entry(statement).or_default();
+            if !group.contains(&signer) {
+                group.push(signer);
+            }
+            agreeing = agreeing.max(group.len());
+            if group.len() >= self.threshold {
+                return Ok(answer);
+            }
+        }
+
+        Err(ZkError::QuorumNotReached {
+            threshold: self.threshold,
+            agreeing,
+        })
+    }
+
+    /// The signer of `answer` if it is trusted and the proof verifies.
+    fn signer(
+        &self,
+        answer: &ProofEnvelope,
+        vk: &VerifyingKey<Bn254>,
+    ) -> Result<Option<PublicKey>, ZkError> {
+        let signer = verify_signature(answer)?;
+        Ok((self.trusted.contains(&signer) && answer.verify(vk)?).then_some(signer))
+    }
+}
+
+fn invalid(reason: String) -> ZkError {
+    ZkError::InvalidInput {
+        field: "quorum",
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_root, DataVerificationEntry, MerkleTreeData, MEMBERSHIP_CIRCUIT_ID};
+    use ark_bn254::Fr as ArkFr;
+    use ark_groth16::Groth16;
+    use ark_std::test_rng;
+
+    /// An unsigned envelope per prover, all for the same statement.
+    fn answers(provers: usize) -> (Vec<ProofEnvelope>, VerifyingKey<Bn254>) {
+        let data = MerkleTreeData {
+            leaf: ArkFr::from(7u64),
+            siblings: vec![ArkFr::from(11u64)],
+            path_bits: vec![false],
+        };
+        let root = compute_root(&data).unwrap();
+        let mut rng = test_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            DataVerificationEntry::blank(1).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+
+        let answers = (0..provers)
+            .map(|i| {
+                let proof = Groth16::<Bn254>::create_random_proof_with_reduction(
+                    DataVerificationEntry::new(&data, root).unwrap(),
+                    &pk,
+                    &mut rng,
+                )
+                .unwrap();
+                let prover = format!("worker-{}", i);
+                ProofEnvelope::new(MEMBERSHIP_CIRCUIT_ID, &pk.vk, &proof, &[root], &prover).unwrap()
+            })
+            .collect();
+        (answers, pk.vk)
+    }
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_signature_covers_envelope() {
+        let (mut answers, _) = answers(1);
+        let mut envelope = answers.remove(0);
+        assert_eq!(verify_signature(&envelope).unwrap_err().code(), 18);
+
+        sign_envelope(&mut envelope, &key(1)).unwrap();
+        assert_eq!(verify_signature(&envelope).unwrap(), key(1).verifying_key());
+        let decoded = ProofEnvelope::from_cbor(&envelope.to_cbor().unwrap()).unwrap();
+        assert_eq!(verify_signature(&decoded).unwrap(), key(1).verifying_key());
+
+        let mut tampered = envelope.clone();
+        tampered.prover = "someone-else".to_string();
+        assert_eq!(verify_signature(&tampered).unwrap_err().code(), 18);
+
+        envelope.signature.as_mut().unwrap().algorithm = "secp256k1".to_string();
+        assert_eq!(verify_signature(&envelope).unwrap_err().code(), 16);
+    }
+
+    #[test]
+    fn test_quorum_counts_distinct_trusted_signers() {
+        let (mut answers, vk) = answers(4);
+        for (answer, seed) in answers.iter_mut().zip([1, 1, 2, 3]) {
+            sign_envelope(answer, &key(seed)).unwrap();
+        }
+        let quorum = Quorum::new(2, vec![key(1).verifying_key(), key(3).verifying_key()]).unwrap();
+
+        // Worker 1 twice and the untrusted worker 2 are one agreeing signer.
+        let err = quorum.accept(&answers[..3], &vk).unwrap_err();
+        assert!(matches!(
+            err,
+            ZkError::QuorumNotReached {
+                threshold: 2,
+                agreeing: 1
+            }
+        ));
+        assert_eq!(quorum.accept(&answers, &vk).unwrap().prover, "worker-3");
+
+        // An answer altered after signing does not count.
+        answers[3].created_at = answers[0].created_at - chrono::Duration::seconds(1);
+        assert_eq!(quorum.accept(&answers, &vk).unwrap_err().code(), 19);
+    }
+
+    #[test]
+    fn test_quorum_requires_trusted_signers() {
+        assert_eq!(Quorum::new(2, Vec::new()).unwrap_err().code(), 14);
+        assert_eq!(
+            Quorum::new(2, vec![key(1).verifying_key()])
+                .unwrap_err()
+                .code(),
+            14
+        );
+        assert_eq!(
+            Quorum::new(0, vec![key(1).verifying_key()])
+                .unwrap_err()
+                .code(),
+            14
+        );
+
+        // Keys made up by one party do not count towards the quorum.
+        let (mut answers, vk) = answers(2);
+        for (answer, seed) in answers.iter_mut().zip([8, 9]) {
+            sign_envelope(answer, &key(seed)).unwrap();
+        }
+        let quorum = Quorum::new(2, vec![key(1).verifying_key(), key(3).verifying_key()]).unwrap();
+        assert_eq!(quorum.accept(&answers, &vk).unwrap_err().code(), 19);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use ark_bn254::Fr as ArkFr;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use tracing::{debug, instrument, trace};
+
+use crate::poseidon::PoseidonGadget;
+use crate::{MerkleTreeData, ZkError};
+
+/// Circuit id of `DataVerificationEntry` in proof envelopes and key files.
+pub const MEMBERSHIP_CIRCUIT_ID: &str = "merkle_membership";
+
+/// Membership circuit: the leaf, siblings and path bits are private, the
+/// root is the only public input and must equal the Poseidon hash of the
+/// path.
+#[derive(Clone)]
+pub struct DataVerificationEntry {
+    leaf: ArkFr,
+    siblings: Vec<ArkFr>,
+    path_bits: Vec<bool>,
+    root: ArkFr,
+    /// Loaded when the circuit is built, so that a parameter error surfaces
+    /// as a `ZkError` rather than inside synthesis.
+    poseidon: Arc<PoseidonGadget>,
+}
+
+impl DataVerificationEntry {
+    /// Circuit proving that `data` is included under `root`.
+    pub fn new(data: &MerkleTreeData, root: ArkFr) -> Result<Self, ZkError> {
+        data.check_lengths()?;
+        Ok(DataVerificationEntry {
+            leaf: data.leaf,
+            siblings: data.siblings.clone(),
+            path_bits: data.path_bits.clone(),
+            root,
+            poseidon: Arc::new(PoseidonGadget::new(2)?),
+        })
+    }
+
+    /// A circuit of the given depth with zeroed witnesses. Only its shape
+    /// matters, so it is used to run the setup.
+    pub fn blank(depth: usize) -> Result<Self, ZkError> {
+        let data = MerkleTreeData {
+            leaf: ArkFr::from(0u64),
+            siblings: vec![ArkFr::from(0u64); depth],
+            path_bits: vec![false; depth],
+        };
+        Self::new(&data, ArkFr::from(0u64))
+    }
+
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+}
+
+/// The root above `leaf` along `siblings` and `path_bits`, hashed as
+/// `compute_root` does it natively. The siblings and path bits become
+/// witnesses.
+pub(crate) fn merkle_root(
+    poseidon: &PoseidonGadget,
+    leaf: FpVar<ArkFr>,
+    siblings: &[ArkFr],
+    path_bits: &[bool],
+) -> Result<FpVar<ArkFr>, SynthesisError> {
+    let cs = leaf.cs();
+    let mut current_hash = leaf;
+    for (i, (sibling_hash, path_bit)) in siblings.iter().zip(path_bits.iter()).enumerate() {
+        let sibling_var = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(*sibling_hash))?;
+        let is_left = Boolean::new_witness(cs.clone(), || Ok(*path_bit))?;
+
+        let left = is_left.select(&current_hash, &sibling_var)?;
+        let right = is_left.select(&sibling_var, &current_hash)?;
+        current_hash = poseidon.hash(&[left, right])?;
+
+        trace!(
+            level = i,
+            path_bit,
+            hash = ?current_hash.value().ok(),
+            "hashed path node"
+        );
+    }
+    Ok(current_hash)
+}
+
+impl ConstraintSynthesizer<ArkFr> for DataVerificationEntry {
+    // Witness values are private: they may only be emitted at trace level.
+    #[instrument(name = "synthesis", skip_all, fields(depth = self.depth()))]
+    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
+        let root_var = FpVar::<ArkFr>::new_input(cs.clone(), || Ok(self.root))?;
+        let leaf = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(self.leaf))?;
+
+        merkle_root(&self.poseidon, leaf, &self.siblings, &self.path_bits)?
+            .enforce_equal(&root_var)?;
+
+        debug!(constraints = cs.num_constraints(), "synthesized");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_root;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn data() -> MerkleTreeData {
+        MerkleTreeData {
+            leaf: ArkFr::from(7u64),
+            siblings: vec![ArkFr::from(11u64), ArkFr::from(13u64)],
+            path_bits: vec![true, false],
+        }
+    }
+
+    fn is_satisfied(circuit: DataVerificationEntry) -> bool {
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_accepts_computed_root() {
+        let data = data();
+        let root = compute_root(&data).unwrap();
+        assert!(is_satisfied(
+            DataVerificationEntry::new(&data, root).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_rejects_other_root() {
+        let data = data();
+        let root = compute_root(&data).unwrap();
+        let other_root = root + ArkFr::from(1u64);
+        assert!(!is_satisfied(
+            DataVerificationEntry::new(&data, other_root).unwrap()
+        ));
+
+        // Same leaf and siblings, other side at the first level.
+        let mut flipped = data.clone();
+        flipped.path_bits[0] = false;
+        assert!(!is_satisfied(
+            DataVerificationEntry::new(&flipped, root).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+//! Versioned, self-describing container for a proof, so that a stored proof
+//! can be checked later without knowing how it was produced.
+//!
+//! JSON carries the byte fields as hex; CBOR carries them as byte strings.
+
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+use crate::{decode_proof, fr_to_hex, parse_fr, ZkError};
+
+/// Version written by this crate; envelopes of any other version are
+/// rejected when decoded.
+pub const ENVELOPE_VERSION: u32 = 1;
+pub const CURVE: &str = "bn254";
+
+/// Proving system named by `ProofEnvelope::proving_system`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvingSystem {
+    Groth16,
+}
+
+impl ProvingSystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProvingSystem::Groth16 => "groth16",
+        }
+    }
+}
+
+impl FromStr for ProvingSystem {
+    type Err = ZkError;
+
+    fn from_str(name: &str) -> Result<Self, ZkError> {
+        match name {
+            "groth16" => Ok(ProvingSystem::Groth16),
+            _ => Err(ZkError::UnsupportedEnvelope {
+                field: "proving_system",
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+/// Key id of a verifying key: hex SHA-256 of its uncompressed encoding.
+pub fn vk_id(vk: &VerifyingKey<Bn254>) -> Result<String, ZkError> {
+    let mut bytes = Vec::new();
+    vk.serialize_uncompressed(&mut bytes)?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvelopeSignature {
+    /// Signature scheme, e.g. `ed25519`.
+    pub algorithm: String,
+    #[serde(with = "bytes")]
+    pub public_key: Vec<u8>,
+    #[serde(with = "bytes")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEnvelope {
+    pub version: u32,
+    pub curve: String,
+    /// See `ProvingSystem`.
+    pub proving_system: String,
+    pub circuit_id: String,
+    /// See `vk_id`.
+    pub vk_id: String,
+    /// Little-endian hex field elements, in circuit order.
+    pub public_inputs: Vec<String>,
+    /// Uncompressed `Proof<Bn254>`.
+    #[serde(with = "bytes")]
+    pub proof: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    /// Who produced the proof, e.g. a Fluence peer id. Empty when unknown.
+    pub prover: String,
+    pub signature: Option<EnvelopeSignature>,
+}
+
+impl ProofEnvelope {
+    /// Unsigned envelope for a Groth16 proof over BN254, created now.
+    pub fn new(
+        circuit_id: &str,
+        vk: &VerifyingKey<Bn254>,
+        proof: &Proof<Bn254>,
+        public_inputs: &[ArkFr],
+        prover: &str,
+    ) -> Result<Self, ZkError> {
+        let mut proof_bytes = Vec::new();
+        proof.serialize_uncompressed(&mut proof_bytes)?;
+
+        Ok(ProofEnvelope {
+            version: ENVELOPE_VERSION,
+            curve: CURVE.to_string(),
+            proving_system: ProvingSystem::Groth16.as_str().to_string(),
+            circuit_id: circuit_id.to_string(),
+            vk_id: vk_id(vk)?,
+            public_inputs: public_inputs.iter().map(fr_to_hex).collect(),
+            proof: proof_bytes,
+            created_at: Utc::now(),
+            prover: prover.to_string(),
+            signature: None,
+        })
+    }
+
+    /// Rejects envelopes this crate cannot interpret.
+    pub fn check_format(&self) -> Result<(), ZkError> {
+        let unsupported = |field, value: String| Err(ZkError::UnsupportedEnvelope { field, value });
+        if self.version != ENVELOPE_VERSION {
+            return unsupported("version", self.version.to_string());
+        }
+        if self.curve != CURVE {
+            return unsupported("curve", self.curve.clone());
+        }
+        self.proving_system()?;
+        Ok(())
+    }
+
+    pub fn proving_system(&self) -> Result<ProvingSystem, ZkError> {
+        self.proving_system.parse()
+    }
+
+    pub fn decode_proof(&self) -> Result<Proof<Bn254>, ZkError> {
+        decode_proof(&self.proof)
+    }
+
+    pub fn decode_public_inputs(&self) -> Result<Vec<ArkFr>, ZkError> {
+        self.public_inputs
+            .iter()
+            .map(|input| parse_fr("public_inputs", input))
+            .collect()
+    }
+
+    /// Checks the proof against `vk`, which must be the key named by
+    /// `vk_id`. The signature, if any, is not checked here.
+    pub fn verify(&self, vk: &VerifyingKey<Bn254>) -> Result<bool, ZkError> {
+        self.check_format()?;
+        match self.proving_system()? {
+            ProvingSystem::Groth16 => self.verify_groth16(vk),
+        }
+    }
+
+    fn verify_groth16(&self, vk: &VerifyingKey<Bn254>) -> Result<bool, ZkError> {
+        if vk_id(vk)? != self.vk_id {
+            return Err(ZkError::InvalidInput {
+                field: "vk_id",
+                reason: "does not match the verifying key".to_string(),
+            });
+        }
+
+        let public_inputs = self.decode_public_inputs()?;
+        if public_inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return Ok(false);
+        }
+        let pvk = ark_groth16::prepare_verifying_key(vk);
+        Groth16::<Bn254>::verify_proof(&pvk, &self.decode_proof()?, &public_inputs)
+            .map_err(ZkError::Verification)
+    }
+
+    /// Bytes covered by `signature`: the CBOR encoding with the signature
+    /// left out.
+    pub fn signed_bytes(&self) -> Result<Vec<u8>, ZkError> {
+        ProofEnvelope {
+            signature: None,
+            ..self.clone()
+        }
+        .to_cbor()
+    }
+
+    pub fn to_json(&self) -> Result<String, ZkError> {
+        serde_json::to_string(self).map_err(|e| ZkError::Encoding(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ZkError> {
+        let envelope: Self =
+            serde_json::from_str(json).map_err(|e| ZkError::Encoding(e.to_string()))?;
+        envelope.check_format()?;
+        Ok(envelope)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ZkError> {
+        let mut cbor = Vec::new();
+        ciborium::into_writer(self, &mut cbor).map_err(|e| ZkError::Encoding(e.to_string()))?;
+        Ok(cbor)
+    }
+
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self, ZkError> {
+        let envelope: Self =
+            ciborium::from_reader(cbor).map_err(|e| ZkError::Encoding(e.to_string()))?;
+        envelope.check_format()?;
+        Ok(envelope)
+    }
+}
+
+/// Hex strings in human-readable formats, byte strings otherwise.
+mod bytes {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let hex_string = String::deserialize(deserializer)?;
+            hex::decode(hex_string).map_err(de::Error::custom)
+        } else {
+            Ok(serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_root, DataVerificationEntry, MerkleTreeData, MEMBERSHIP_CIRCUIT_ID};
+    use ark_std::test_rng;
+
+    fn envelope() -> (ProofEnvelope, VerifyingKey<Bn254>) {
+        let data = MerkleTreeData {
+            leaf: ArkFr::from(7u64),
+            siblings: vec![ArkFr::from(11u64)],
+            path_bits: vec![true],
+        };
+        let root = compute_root(&data).unwrap();
+        let mut rng = test_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            DataVerificationEntry::blank(1).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(
+            DataVerificationEntry::new(&data, root).unwrap(),
+            &pk,
+            &mut rng,
+        )
+        .unwrap();
+
+        let envelope =
+            ProofEnvelope::new(MEMBERSHIP_CIRCUIT_ID, &pk.vk, &proof, &[root], "test").unwrap();
+        (envelope, pk.vk)
+    }
+
+    #[test]
+    fn test_round_trips_and_verifies() {
+        let (envelope, vk) = envelope();
+        assert!(envelope.verify(&vk).unwrap());
+
+        let json = envelope.to_json().unwrap();
+        assert_eq!(ProofEnvelope::from_json(&json).unwrap(), envelope);
+        let cbor = envelope.to_cbor().unwrap();
+        assert_eq!(ProofEnvelope::from_cbor(&cbor).unwrap(), envelope);
+
+        let mut other_input = envelope.clone();
+        other_input.public_inputs = vec![fr_to_hex(&ArkFr::from(1u64))];
+        assert!(!other_input.verify(&vk).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_unknown_format() {
+        let (mut envelope, _) = envelope();
+        envelope.version = ENVELOPE_VERSION + 1;
+        let json = envelope.to_json().unwrap();
+        assert_eq!(ProofEnvelope::from_json(&json).unwrap_err().code(), 16);
+
+        envelope.version = ENVELOPE_VERSION;
+        envelope.curve = "bls12_381".to_string();
+        assert_eq!(envelope.check_format().unwrap_err().code(), 16);
+
+        envelope.curve = CURVE.to_string();
+        envelope.proving_system = "marlin".to_string();
+        assert_eq!(envelope.check_format().unwrap_err().code(), 16);
+        assert_eq!(
+            "groth16".parse::<ProvingSystem>().unwrap().as_str(),
+            "groth16"
+        );
+    }
+}
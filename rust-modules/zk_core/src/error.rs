@@ -0,0 +1,102 @@
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use light_poseidon::PoseidonError;
+use thiserror::Error;
+
+/// Failures of parsing, hashing, proving and verifying.
+///
+/// `code` is reported unchanged by the Fluence service and the wasm
+/// bindings, so a variant keeps its code forever. Codes 3, 4, 8 to 12 and
+/// 20 are taken by `myService`'s own errors.
+#[derive(Debug, Error)]
+pub enum ZkError {
+    #[error("invalid hex in {field}: {source}")]
+    InvalidHex {
+        field: &'static str,
+        source: hex::FromHexError,
+    },
+    #[error("siblings and path_bits differ in length ({siblings} != {path_bits})")]
+    LengthMismatch { siblings: usize, path_bits: usize },
+    #[error("{field} is not a canonical field element: {len} bytes, expected at most 32 little-endian bytes below the modulus")]
+    NonCanonical { field: &'static str, len: usize },
+    #[error("invalid {field}: {reason}")]
+    InvalidInput { field: &'static str, reason: String },
+    #[error("poseidon: {0}")]
+    Poseidon(#[from] PoseidonError),
+    #[error("synthesis failed: {0}")]
+    Synthesis(#[from] SynthesisError),
+    #[error("(de)serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+    #[error("verification failed: {0}")]
+    Verification(SynthesisError),
+    #[error("unsupported envelope {field}: {value}")]
+    UnsupportedEnvelope { field: &'static str, value: String },
+    #[error("envelope encoding failed: {0}")]
+    Encoding(String),
+    #[error("bad envelope signature: {0}")]
+    Signature(String),
+    #[error("only {agreeing} of the {threshold} required answers agree")]
+    QuorumNotReached { threshold: usize, agreeing: usize },
+}
+
+impl ZkError {
+    pub fn code(&self) -> u32 {
+        match self {
+            ZkError::InvalidHex { .. } => 1,
+            ZkError::LengthMismatch { .. } => 2,
+            ZkError::Poseidon(_) => 5,
+            ZkError::Synthesis(_) => 6,
+            ZkError::Serialization(_) => 7,
+            ZkError::NonCanonical { .. } => 13,
+            ZkError::InvalidInput { .. } => 14,
+            ZkError::Verification(_) => 15,
+            ZkError::UnsupportedEnvelope { .. } => 16,
+            ZkError::Encoding(_) => 17,
+            ZkError::Signature(_) => 18,
+            ZkError::QuorumNotReached { .. } => 19,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable() {
+        let errors = [
+            ZkError::InvalidHex {
+                field: "leaf",
+                source: hex::FromHexError::OddLength,
+            },
+            ZkError::LengthMismatch {
+                siblings: 1,
+                path_bits: 2,
+            },
+            ZkError::Poseidon(PoseidonError::EmptyInput),
+            ZkError::Synthesis(SynthesisError::Unsatisfiable),
+            ZkError::Serialization(SerializationError::InvalidData),
+            ZkError::NonCanonical {
+                field: "leaf",
+                len: 32,
+            },
+            ZkError::InvalidInput {
+                field: "seed",
+                reason: String::new(),
+            },
+            ZkError::Verification(SynthesisError::MalformedVerifyingKey),
+            ZkError::UnsupportedEnvelope {
+                field: "version",
+                value: "2".to_string(),
+            },
+            ZkError::Encoding(String::new()),
+            ZkError::Signature(String::new()),
+            ZkError::QuorumNotReached {
+                threshold: 2,
+                agreeing: 1,
+            },
+        ];
+        let codes: Vec<u32> = errors.iter().map(ZkError::code).collect();
+        assert_eq!(codes, [1, 2, 5, 6, 7, 13, 14, 15, 16, 17, 18, 19]);
+    }
+}
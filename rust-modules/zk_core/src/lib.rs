@@ -0,0 +1,219 @@
+//! Merkle membership circuit and helpers shared by `zk_snarks` and the
+//! Fluence `myService` module, so that every deployment proves and verifies
+//! against the same constraint system.
+//!
+//! Field elements are exchanged as little-endian hex. A `true` path bit means
+//! the current node is the left child at that level.
+//!
+//! The `std` feature is on by default and currently required, since errors
+//! implement `std::error::Error`.
+
+#[cfg(not(feature = "std"))]
+compile_error!("zk_core needs the `std` feature");
+
+use ark_bn254::{Bn254, Fq as ArkFq, Fr as ArkFr};
+use ark_ec::pairing::Pairing;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{debug, instrument};
+
+pub mod aggregates;
+pub mod attestation;
+pub mod circuit;
+pub mod envelope;
+pub mod error;
+pub mod ownership;
+pub mod poseidon;
+pub mod range;
+
+pub use aggregates::{AggregateCircuit, Aggregates, DatedRecord, AGGREGATE_CIRCUIT_ID};
+pub use attestation::{sign_envelope, verify_signature, Quorum};
+pub use circuit::{DataVerificationEntry, MEMBERSHIP_CIRCUIT_ID};
+pub use envelope::{vk_id, EnvelopeSignature, ProofEnvelope, ProvingSystem};
+pub use error::ZkError;
+pub use ownership::{identity_commitment, nullifier, OwnershipCircuit, OWNERSHIP_CIRCUIT_ID};
+pub use range::{AmountRangeCircuit, AmountStatement, RecordFields, RANGE_CIRCUIT_ID};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MyFqWrapper(pub ArkFq);
+
+impl Serialize for MyFqWrapper {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut bytes = Vec::new();
+        self.0
+            .serialize_uncompressed(&mut bytes)
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for MyFqWrapper {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes: &[u8] = Deserialize::deserialize(deserializer)?;
+        let fq = ArkFq::deserialize_uncompressed(bytes).map_err(serde::de::Error::custom)?;
+        Ok(MyFqWrapper(fq))
+    }
+}
+
+/// A leaf and its authentication path, from the leaf level up to the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTreeData {
+    pub leaf: ArkFr,
+    pub siblings: Vec<ArkFr>,
+    /// `true` when the current node is the left child at that level.
+    pub path_bits: Vec<bool>,
+}
+
+impl MerkleTreeData {
+    fn check_lengths(&self) -> Result<(), ZkError> {
+        if self.siblings.len() != self.path_bits.len() {
+            return Err(ZkError::LengthMismatch {
+                siblings: self.siblings.len(),
+                path_bits: self.path_bits.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a field element from little-endian hex. Values of more than 32
+/// bytes or not below the modulus are rejected rather than reduced, so every
+/// element has exactly one accepted encoding (up to trailing zero bytes).
+pub fn parse_fr(field: &'static str, value: &str) -> Result<ArkFr, ZkError> {
+    let bytes = hex::decode(value).map_err(|source| ZkError::InvalidHex { field, source })?;
+    let non_canonical = || ZkError::NonCanonical {
+        field,
+        len: bytes.len(),
+    };
+    if bytes.len() > 32 {
+        return Err(non_canonical());
+    }
+
+    let mut padded = [0u8; 32];
+    padded[..bytes.len()].copy_from_slice(&bytes);
+    ArkFr::deserialize_uncompressed(&padded[..]).map_err(|_| non_canonical())
+}
+
+/// Decodes a membership witness from little-endian hex field elements.
+pub fn parse_data(
+    leaf_hex: &str,
+    siblings_hex: &[String],
+    path_bits: &[bool],
+) -> Result<MerkleTreeData, ZkError> {
+    if siblings_hex.len() != path_bits.len() {
+        return Err(ZkError::LengthMismatch {
+            siblings: siblings_hex.len(),
+            path_bits: path_bits.len(),
+        });
+    }
+
+    let leaf = parse_fr("leaf", leaf_hex)?;
+    let siblings = siblings_hex
+        .iter()
+        .map(|sibling| parse_fr("siblings", sibling))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(MerkleTreeData {
+        leaf,
+        siblings,
+        path_bits: path_bits.to_vec(),
+    })
+}
+
+/// Encodes a field element as little-endian hex, the inverse of `parse_fr`.
+pub fn fr_to_hex(value: &ArkFr) -> String {
+    hex::encode(value.into_bigint().to_bytes_le())
+}
+
+/// Hashes the leaf up the path and returns the resulting root.
+pub fn compute_root(data: &MerkleTreeData) -> Result<ArkFr, ZkError> {
+    data.check_lengths()?;
+    let mut hasher = Poseidon::<ArkFr>::new_circom(2)?;
+    let mut current_hash = data.leaf;
+
+    for (sibling_hash, path_bit) in data.siblings.iter().zip(data.path_bits.iter()) {
+        let (left, right) = if *path_bit {
+            (current_hash, *sibling_hash)
+        } else {
+            (*sibling_hash, current_hash)
+        };
+
+        current_hash = hasher.hash(&[left, right])?;
+    }
+
+    debug!(root = %fr_to_hex(&current_hash), "recomputed Merkle root");
+    Ok(current_hash)
+}
+
+/// Whether the path in `data` hashes to `root`.
+pub fn verify_merkle_tree(data: &MerkleTreeData, root: &ArkFr) -> Result<bool, ZkError> {
+    Ok(compute_root(data)? == *root)
+}
+
+/// Uncompressed proof bytes, hex encoded.
+#[instrument(name = "serialization", level = "debug", skip_all)]
+pub fn serialize_proof<E: Pairing>(proof: &Proof<E>) -> Result<String, ZkError> {
+    let mut proof_bytes = vec![];
+    proof.serialize_uncompressed(&mut proof_bytes)?;
+    Ok(hex::encode(proof_bytes))
+}
+
+/// Inverse of `serialize_proof`.
+pub fn deserialize_proof(proof_hex: &str) -> Result<Proof<Bn254>, ZkError> {
+    let proof_bytes = hex::decode(proof_hex).map_err(|source| ZkError::InvalidHex {
+        field: "proof",
+        source,
+    })?;
+    decode_proof(&proof_bytes)
+}
+
+/// Reads an uncompressed proof, checking that every point is on the curve
+/// and in the right subgroup.
+#[instrument(name = "deserialization", level = "debug", skip_all)]
+pub fn decode_proof(proof_bytes: &[u8]) -> Result<Proof<Bn254>, ZkError> {
+    Ok(Proof::deserialize_uncompressed(proof_bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fr_round_trips() {
+        let value = ArkFr::from(123_456_789u64);
+        assert_eq!(parse_fr("leaf", &fr_to_hex(&value)).unwrap(), value);
+        assert_eq!(parse_fr("leaf", "15").unwrap(), ArkFr::from(21u64));
+    }
+
+    #[test]
+    fn test_parse_fr_rejects_non_canonical() {
+        // The modulus itself, little-endian.
+        let modulus = hex::encode(ArkFr::MODULUS.to_bytes_le());
+        assert_eq!(parse_fr("leaf", &modulus).unwrap_err().code(), 13);
+        assert_eq!(parse_fr("leaf", &"00".repeat(33)).unwrap_err().code(), 13);
+        assert_eq!(parse_fr("leaf", "abc").unwrap_err().code(), 1);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let siblings = vec![fr_to_hex(&ArkFr::from(1u64))];
+        let err = parse_data("01", &siblings, &[true, false]).unwrap_err();
+        assert_eq!(err.code(), 2);
+
+        let data = MerkleTreeData {
+            leaf: ArkFr::from(1u64),
+            siblings: vec![ArkFr::from(2u64)],
+            path_bits: vec![],
+        };
+        assert_eq!(compute_root(&data).unwrap_err().code(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+//! One-time proofs of upload ownership, after Semaphore.
+//!
+//! An owner keeps a random `secret` and publishes only its identity
+//! commitment `Poseidon(secret)` as a leaf of the Poseidon tree. A proof
+//! shows that the prover knows the secret of some leaf under `root` and
+//! reveals `nullifier = Poseidon(secret, external_nullifier)`: the same for
+//! every proof in one context, unlinkable across contexts. A verifier that
+//! accepts each nullifier once gets one proof per owner and context without
+//! learning which leaf was used.
+//!
+//! `signal` is bound to the proof without constraining it, e.g. to the
+//! message or recipient the proof authorizes, so that it cannot be replayed
+//! with another.
+
+use std::sync::Arc;
+
+use ark_bn254::Fr as ArkFr;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use tracing::{debug, instrument};
+
+use crate::circuit::merkle_root;
+use crate::poseidon::PoseidonGadget;
+use crate::{compute_root, MerkleTreeData, ZkError};
+
+/// Circuit id of `OwnershipCircuit` in proof envelopes and key files.
+pub const OWNERSHIP_CIRCUIT_ID: &str = "upload_ownership";
+
+/// The leaf an owner publishes: `Poseidon(secret)`.
+pub fn identity_commitment(secret: &ArkFr) -> Result<ArkFr, ZkError> {
+    Ok(Poseidon::<ArkFr>::new_circom(1)?.hash(&[*secret])?)
+}
+
+/// `Poseidon(secret, external_nullifier)`.
+pub fn nullifier(secret: &ArkFr, external_nullifier: &ArkFr) -> Result<ArkFr, ZkError> {
+    Ok(Poseidon::<ArkFr>::new_circom(2)?.hash(&[*secret, *external_nullifier])?)
+}
+
+/// Ownership circuit: the secret and its path are private; the root,
+/// nullifier, external nullifier and signal are public, in that order.
+#[derive(Clone)]
+pub struct OwnershipCircuit {
+    secret: ArkFr,
+    siblings: Vec<ArkFr>,
+    path_bits: Vec<bool>,
+    root: ArkFr,
+    nullifier: ArkFr,
+    external_nullifier: ArkFr,
+    signal: ArkFr,
+    commitment_hash: Arc<PoseidonGadget>,
+    pair_hash: Arc<PoseidonGadget>,
+}
+
+impl OwnershipCircuit {
+    /// Circuit proving ownership of the commitment of `secret`, found under
+    /// the given path, in the context `external_nullifier`.
+    pub fn new(
+        secret: ArkFr,
+        siblings: &[ArkFr],
+        path_bits: &[bool],
+        external_nullifier: ArkFr,
+        signal: ArkFr,
+    ) -> Result<Self, ZkError> {
+        let path = MerkleTreeData {
+            leaf: identity_commitment(&secret)?,
+            siblings: siblings.to_vec(),
+            path_bits: path_bits.to_vec(),
+        };
+        Ok(OwnershipCircuit {
+            root: compute_root(&path)?,
+            nullifier: nullifier(&secret, &external_nullifier)?,
+            secret,
+            siblings: path.siblings,
+            path_bits: path.path_bits,
+            external_nullifier,
+            signal,
+            commitment_hash: Arc::new(PoseidonGadget::new(1)?),
+            pair_hash: Arc::new(PoseidonGadget::new(2)?),
+        })
+    }
+
+    /// A circuit of the given depth with zeroed witnesses, to run the setup.
+    pub fn blank(depth: usize) -> Result<Self, ZkError> {
+        let zero = ArkFr::from(0u64);
+        Self::new(zero, &vec![zero; depth], &vec![false; depth], zero, zero)
+    }
+
+    pub fn public_inputs(&self) -> Vec<ArkFr> {
+        vec![
+            self.root,
+            self.nullifier,
+            self.external_nullifier,
+            self.signal,
+        ]
+    }
+}
+
+impl ConstraintSynthesizer<ArkFr> for OwnershipCircuit {
+    // Witness values are private: they may only be emitted at trace level.
+    #[instrument(name = "synthesis", skip_all, fields(depth = self.siblings.len()))]
+    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
+        let root = FpVar::<ArkFr>::new_input(cs.clone(), || Ok(self.root))?;
+        let nullifier = FpVar::<ArkFr>::new_input(cs.clone(), || Ok(self.nullifier))?;
+        let external_nullifier =
+            FpVar::<ArkFr>::new_input(cs.clone(), || Ok(self.external_nullifier))?;
+        let signal = FpVar::<ArkFr>::new_input(cs.clone(), || Ok(self.signal))?;
+        let secret = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(self.secret))?;
+
+        // An input that no constraint uses would not be bound to the proof.
+        let _signal_square = signal.square()?;
+
+        self.pair_hash
+            .hash(&[secret.clone(), external_nullifier])?
+            .enforce_equal(&nullifier)?;
+
+        let commitment = self.commitment_hash.hash(&[secret])?;
+        merkle_root(&self.pair_hash, commitment, &self.siblings, &self.path_bits)?
+            .enforce_equal(&root)?;
+
+        debug!(constraints = cs.num_constraints(), "synthesized");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+
+    const SECRET: u64 = 0x5ec2e7;
+
+    fn circuit(external_nullifier: u64) -> OwnershipCircuit {
+        let siblings = [ArkFr::from(11u64), ArkFr::from(13u64)];
+        OwnershipCircuit::new(
+            ArkFr::from(SECRET),
+            &siblings,
+            &[false, true],
+            ArkFr::from(external_nullifier),
+            ArkFr::from(42u64),
+        )
+        .unwrap()
+    }
+
+    fn is_satisfied(circuit: OwnershipCircuit) -> bool {
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_nullifier_depends_on_context_only() {
+        let (first, second) = (circuit(1), circuit(2));
+        assert_eq!(first.root, second.root);
+        assert_ne!(first.nullifier, second.nullifier);
+        assert_eq!(first.nullifier, circuit(1).nullifier);
+        assert!(is_satisfied(first));
+    }
+
+    #[test]
+    fn test_rejects_forged_inputs() {
+        let mut other_nullifier = circuit(1);
+        other_nullifier.nullifier = circuit(2).nullifier;
+        assert!(!is_satisfied(other_nullifier));
+
+        // A secret whose commitment is not in the tree.
+        let mut other_secret = circuit(1);
+        other_secret.secret = ArkFr::from(SECRET + 1);
+        other_secret.nullifier = nullifier(&other_secret.secret, &ArkFr::from(1u64)).unwrap();
+        assert!(!is_satisfied(other_secret));
+    }
+
+    #[test]
+    fn test_groth16_binds_signal() {
+        let mut rng = test_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            OwnershipCircuit::blank(2).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+        let circuit = circuit(1);
+        let inputs = circuit.public_inputs();
+        let proof =
+            Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, &mut rng).unwrap();
+
+        let pvk = ark_groth16::prepare_verifying_key(&pk.vk);
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &inputs).unwrap());
+        let mut other_signal = inputs;
+        other_signal[3] = ArkFr::from(43u64);
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other_signal).unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+//! Poseidon as R1CS constraints, equal to `light_poseidon`'s circom-compatible
+//! hash over BN254 for the same number of inputs.
+
+use ark_bn254::Fr as ArkFr;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use light_poseidon::parameters::bn254_x5::get_poseidon_parameters;
+use light_poseidon::{PoseidonError, PoseidonParameters};
+use tracing::debug;
+
+pub struct PoseidonGadget {
+    params: PoseidonParameters<ArkFr>,
+}
+
+impl PoseidonGadget {
+    /// Gadget for hashing exactly `nr_inputs` field elements, as
+    /// `Poseidon::<Fr>::new_circom(nr_inputs)` does natively.
+    pub fn new(nr_inputs: usize) -> Result<Self, PoseidonError> {
+        let width = u8::try_from(nr_inputs + 1).map_err(|_| PoseidonError::U64Tou8)?;
+        Ok(PoseidonGadget {
+            params: get_poseidon_parameters::<ArkFr>(width)?,
+        })
+    }
+
+    /// Hashes `inputs`, which must number as many as the gadget was created
+    /// for; otherwise the circuit is reported `Unsatisfiable`.
+    pub fn hash(&self, inputs: &[FpVar<ArkFr>]) -> Result<FpVar<ArkFr>, SynthesisError> {
+        let width = self.params.width;
+        if inputs.len() != width - 1 {
+            debug!(
+                inputs = inputs.len(),
+                width, "wrong number of Poseidon inputs"
+            );
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // The circom domain tag is zero.
+        let mut state: Vec<FpVar<ArkFr>> = std::iter::once(FpVar::zero())
+            .chain(inputs.iter().cloned())
+            .collect();
+
+        let half_rounds = self.params.full_rounds / 2;
+        let partial_rounds = half_rounds..half_rounds + self.params.partial_rounds;
+        for round in 0..self.params.full_rounds + self.params.partial_rounds {
+            for (i, element) in state.iter_mut().enumerate() {
+                *element += self.params.ark[round * width + i];
+            }
+
+            if partial_rounds.contains(&round) {
+                state[0] = state[0].pow_by_constant([self.params.alpha])?;
+            } else {
+                for element in state.iter_mut() {
+                    *element = element.pow_by_constant([self.params.alpha])?;
+                }
+            }
+
+            state = self
+                .params
+                .mds
+                .iter()
+                .map(|row| {
+                    state
+                        .iter()
+                        .zip(row)
+                        .fold(FpVar::zero(), |acc, (element, m)| acc + element * *m)
+                })
+                .collect();
+        }
+
+        Ok(state.swap_remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::{test_rng, UniformRand};
+    use light_poseidon::{Poseidon, PoseidonHasher};
+
+    #[test]
+    fn test_matches_native_hash() {
+        let mut rng = test_rng();
+        for nr_inputs in 1..=3 {
+            let inputs: Vec<ArkFr> = (0..nr_inputs).map(|_| ArkFr::rand(&mut rng)).collect();
+            let expected = Poseidon::<ArkFr>::new_circom(nr_inputs)
+                .unwrap()
+                .hash(&inputs)
+                .unwrap();
+
+            let cs = ConstraintSystem::<ArkFr>::new_ref();
+            let vars = inputs
+                .iter()
+                .map(|input| FpVar::new_witness(cs.clone(), || Ok(*input)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let hash = PoseidonGadget::new(nr_inputs).unwrap().hash(&vars).unwrap();
+
+            assert_eq!(hash.value().unwrap(), expected);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_rejects_wrong_input_count() {
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        let one = FpVar::new_witness(cs, || Ok(ArkFr::from(1u64))).unwrap();
+        assert!(PoseidonGadget::new(2).unwrap().hash(&[one]).is_err());
+    }
+}
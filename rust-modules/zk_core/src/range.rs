@@ -0,0 +1,352 @@
+//! Range proofs over the amounts of a committed transaction upload.
+//!
+//! The circuit rebuilds the upload's record tree from every record's six
+//! encoded fields, as wasm-lib's `LeafHasher::encode_fields` produces them,
+//! and proves that each selected record's amount lies in `[row_min,
+//! row_max]` and that their total lies in `[sum_min, sum_max]`. Records are
+//! selected by type, or all of them. Only the root and the statement are
+//! public.
+//!
+//! Amounts are fixed-point integers in cents (see `AMOUNT_SCALE`); negative
+//! amounts wrap around the modulus, as in wasm-lib.
+
+use std::sync::Arc;
+
+use ark_bn254::Fr as ArkFr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use tracing::{debug, instrument};
+
+use crate::poseidon::PoseidonGadget;
+use crate::ZkError;
+
+/// Circuit id of `AmountRangeCircuit` in proof envelopes and key files.
+pub const RANGE_CIRCUIT_ID: &str = "amount_range";
+
+/// Scale of the fixed-point amounts, in cents. wasm-lib commits amounts
+/// with `fixed_amount` and `signed_fr` from here, so both sides agree.
+pub const AMOUNT_SCALE: f64 = 100.0;
+
+/// A committed record: `transaction_id`, `H(date)`, `customer_id`, amount,
+/// `H(type)` and `H(description)`, in wasm-lib's `FIELD_NAMES` order.
+pub type RecordFields = [ArkFr; 6];
+pub const AMOUNT_FIELD: usize = 3;
+pub const TYPE_FIELD: usize = 4;
+
+/// Bits of a checked difference between two amounts.
+pub(crate) const AMOUNT_BITS: usize = 64;
+
+/// `amount` in cents, rounded.
+pub fn fixed_amount(amount: f64) -> i64 {
+    (amount * AMOUNT_SCALE).round() as i64
+}
+
+/// A signed amount as a field element; negative values wrap around the
+/// modulus.
+pub fn signed_fr(value: i64) -> ArkFr {
+    if value < 0 {
+        -ArkFr::from(value.unsigned_abs())
+    } else {
+        ArkFr::from(value as u64)
+    }
+}
+
+/// Root of the record tree of `depth` levels over `records`, padded with
+/// zero leaves. It equals wasm-lib's `merkle_root` when `depth` is the
+/// smallest that fits the records.
+pub fn records_root(records: &[RecordFields], depth: usize) -> Result<ArkFr, ZkError> {
+    if records.len() > 1 << depth {
+        return Err(ZkError::InvalidInput {
+            field: "records",
+            reason: format!("{} records do not fit depth {}", records.len(), depth),
+        });
+    }
+    let mut record_hasher = Poseidon::<ArkFr>::new_circom(6)?;
+    let mut pair_hasher = Poseidon::<ArkFr>::new_circom(2)?;
+
+    let mut level = records
+        .iter()
+        .map(|record| record_hasher.hash(record))
+        .collect::<Result<Vec<_>, _>>()?;
+    level.resize(1 << depth, ArkFr::from(0u64));
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| pair_hasher.hash(pair))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(level[0])
+}
+
+/// The public part of a range proof: bounds in cents and the type filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountStatement {
+    pub row_min: i64,
+    pub row_max: i64,
+    /// `H(type)` of the records the statement is about; every record when
+    /// `None`.
+    pub type_hash: Option<ArkFr>,
+    pub sum_min: i64,
+    pub sum_max: i64,
+}
+
+impl AmountStatement {
+    /// Public inputs in circuit order: the root, then the statement.
+    pub fn public_inputs(&self, root: ArkFr) -> Vec<ArkFr> {
+        vec![
+            root,
+            signed_fr(self.row_min),
+            signed_fr(self.row_max),
+            self.type_hash.unwrap_or_default(),
+            signed_fr(self.sum_min),
+            signed_fr(self.sum_max),
+        ]
+    }
+}
+
+/// Range circuit for uploads of up to `2^depth` records.
+#[derive(Clone)]
+pub struct AmountRangeCircuit {
+    records: Vec<RecordFields>,
+    depth: usize,
+    root: ArkFr,
+    statement: AmountStatement,
+    record_hash: Arc<PoseidonGadget>,
+    pair_hash: Arc<PoseidonGadget>,
+}
+
+impl AmountRangeCircuit {
+    /// Circuit proving `statement` about `records`, committed under a tree
+    /// of `depth` levels.
+    pub fn new(
+        records: &[RecordFields],
+        depth: usize,
+        statement: AmountStatement,
+    ) -> Result<Self, ZkError> {
+        Ok(AmountRangeCircuit {
+            root: records_root(records, depth)?,
+            records: records.to_vec(),
+            depth,
+            statement,
+            record_hash: Arc::new(PoseidonGadget::new(6)?),
+            pair_hash: Arc::new(PoseidonGadget::new(2)?),
+        })
+    }
+
+    /// A circuit of the given depth with an empty upload, to run the setup.
+    pub fn blank(depth: usize) -> Result<Self, ZkError> {
+        let statement = AmountStatement {
+            row_min: 0,
+            row_max: 0,
+            type_hash: None,
+            sum_min: 0,
+            sum_max: 0,
+        };
+        Self::new(&[], depth, statement)
+    }
+
+    pub fn root(&self) -> ArkFr {
+        self.root
+    }
+
+    pub fn public_inputs(&self) -> Vec<ArkFr> {
+        self.statement.public_inputs(self.root)
+    }
+}
+
+/// Enforces `0 <= value < 2^bits`.
+pub(crate) fn enforce_bits(value: &FpVar<ArkFr>, bits: usize) -> Result<(), SynthesisError> {
+    let cs = value.cs();
+    let mut sum = FpVar::<ArkFr>::zero();
+    let mut power = ArkFr::from(1u64);
+    for i in 0..bits {
+        let bit = Boolean::new_witness(cs.clone(), || Ok(value.value()?.into_bigint().get_bit(i)))?;
+        sum += FpVar::from(bit) * power;
+        power.double_in_place();
+    }
+    sum.enforce_equal(value)
+}
+
+/// Enforces `min <= value <= max` for values less than `2^bits` apart.
+pub(crate) fn enforce_range(
+    value: &FpVar<ArkFr>,
+    min: &FpVar<ArkFr>,
+    max: &FpVar<ArkFr>,
+    bits: usize,
+) -> Result<(), SynthesisError> {
+    enforce_bits(&(value - min), bits)?;
+    enforce_bits(&(max - value), bits)
+}
+
+impl ConstraintSynthesizer<ArkFr> for AmountRangeCircuit {
+    // Witness values are private: they may only be emitted at trace level.
+    #[instrument(name = "synthesis", skip_all, fields(depth = self.depth))]
+    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
+        let [root, row_min, row_max, type_hash, sum_min, sum_max] = self
+            .public_inputs()
+            .into_iter()
+            .map(|value| FpVar::<ArkFr>::new_input(cs.clone(), || Ok(value)))
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()
+            .map_err(|_| SynthesisError::Unsatisfiable)?;
+        let all_types = type_hash.is_zero()?;
+
+        let mut leaves = Vec::with_capacity(1 << self.depth);
+        let mut sum = FpVar::<ArkFr>::zero();
+        for slot in 0..1 << self.depth {
+            let record = self.records.get(slot);
+            let present = Boolean::new_witness(cs.clone(), || Ok(record.is_some()))?;
+            let fields = (0..6)
+                .map(|i| {
+                    FpVar::new_witness(cs.clone(), || {
+                        Ok(record.map_or(ArkFr::from(0u64), |fields| fields[i]))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // A padding slot must stay a zero leaf, so no committed record
+            // can be left out.
+            let leaf = self.record_hash.hash(&fields)?;
+            leaves.push(present.select(&leaf, &FpVar::zero())?);
+
+            let of_type = all_types.or(&fields[TYPE_FIELD].is_eq(&type_hash)?)?;
+            let selected = present.and(&of_type)?;
+            let amount = &fields[AMOUNT_FIELD];
+            enforce_range(
+                &selected.select(amount, &row_min)?,
+                &row_min,
+                &row_max,
+                AMOUNT_BITS,
+            )?;
+            sum += selected.select(amount, &FpVar::zero())?;
+        }
+        enforce_range(&sum, &sum_min, &sum_max, AMOUNT_BITS + self.depth)?;
+
+        while leaves.len() > 1 {
+            leaves = leaves
+                .chunks(2)
+                .map(|pair| self.pair_hash.hash(pair))
+                .collect::<Result<_, _>>()?;
+        }
+        leaves[0].enforce_equal(&root)?;
+
+        debug!(constraints = cs.num_constraints(), "synthesized");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+
+    const DEBIT: u64 = 1;
+    const CREDIT: u64 = 2;
+
+    fn records() -> Vec<RecordFields> {
+        [(1, 12.5, DEBIT), (2, 80.0, CREDIT), (3, -4.25, DEBIT)]
+            .iter()
+            .map(|&(id, amount, type_hash)| {
+                [
+                    ArkFr::from(id),
+                    ArkFr::from(100 + id),
+                    ArkFr::from(7u64),
+                    signed_fr(fixed_amount(amount)),
+                    ArkFr::from(type_hash),
+                    ArkFr::from(200 + id),
+                ]
+            })
+            .collect()
+    }
+
+    fn statement() -> AmountStatement {
+        AmountStatement {
+            row_min: fixed_amount(-10.0),
+            row_max: fixed_amount(100.0),
+            type_hash: None,
+            sum_min: fixed_amount(88.25),
+            sum_max: fixed_amount(88.25),
+        }
+    }
+
+    fn is_satisfied(statement: AmountStatement) -> bool {
+        let circuit = AmountRangeCircuit::new(&records(), 2, statement).unwrap();
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_accepts_true_statements() {
+        assert!(is_satisfied(statement()));
+
+        let debits = AmountStatement {
+            row_min: 0,
+            type_hash: Some(ArkFr::from(DEBIT)),
+            ..statement()
+        };
+        // The -4.25 debit is below `row_min`.
+        assert!(!is_satisfied(debits));
+        assert!(is_satisfied(AmountStatement {
+            row_min: fixed_amount(-5.0),
+            sum_min: fixed_amount(8.0),
+            sum_max: fixed_amount(8.5),
+            ..debits
+        }));
+    }
+
+    #[test]
+    fn test_rejects_false_statements() {
+        assert!(!is_satisfied(AmountStatement {
+            row_max: fixed_amount(79.99),
+            ..statement()
+        }));
+        assert!(!is_satisfied(AmountStatement {
+            sum_max: fixed_amount(88.24),
+            ..statement()
+        }));
+        assert!(!is_satisfied(AmountStatement {
+            sum_min: fixed_amount(88.26),
+            sum_max: fixed_amount(1000.0),
+            ..statement()
+        }));
+    }
+
+    #[test]
+    fn test_rejects_other_root() {
+        let mut circuit = AmountRangeCircuit::new(&records(), 2, statement()).unwrap();
+        // The upload without its last record.
+        circuit.root = records_root(&records()[..2], 2).unwrap();
+        let cs = ConstraintSystem::<ArkFr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        assert_eq!(records_root(&records(), 1).unwrap_err().code(), 14);
+    }
+
+    #[test]
+    fn test_groth16_round_trip() {
+        let mut rng = test_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            AmountRangeCircuit::blank(2).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+        let circuit = AmountRangeCircuit::new(&records(), 2, statement()).unwrap();
+        let inputs = circuit.public_inputs();
+        let proof =
+            Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, &mut rng).unwrap();
+
+        let pvk = ark_groth16::prepare_verifying_key(&pk.vk);
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &inputs).unwrap());
+        let mut other = inputs;
+        other[5] = signed_fr(fixed_amount(1000.0));
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other).unwrap());
+    }
+}
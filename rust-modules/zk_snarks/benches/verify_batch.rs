@@ -0,0 +1,46 @@
+//! `verify_batch` against `Groth16::verify_proof` in a loop, with the
+//! verifying key prepared once. Run with `cargo bench -p zk_snarks`.
+
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_groth16::Groth16;
+use ark_std::rand::SeedableRng;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand_chacha::ChaCha20Rng;
+use zk_snarks::{create_proof, generate_proving_key, public_inputs, verify_batch, MerkleTreeData};
+
+fn bench_verify(c: &mut Criterion) {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let pk = generate_proving_key(4, &mut rng).unwrap();
+    let items: Vec<_> = (0..64u64)
+        .map(|i| {
+            let data = MerkleTreeData {
+                leaf: ArkFr::from(i),
+                siblings: (0..4).map(|j| ArkFr::from(1000 * i + j)).collect(),
+                path_bits: vec![true, false, i % 2 == 0, true],
+            };
+            let proof = create_proof(&pk, &data, &mut rng).unwrap();
+            (proof, public_inputs(&data).unwrap())
+        })
+        .collect();
+
+    let pvk = ark_groth16::prepare_verifying_key(&pk.vk);
+
+    let mut group = c.benchmark_group("verify");
+    for size in [1, 8, 64] {
+        let batch = &items[..size];
+        group.bench_with_input(BenchmarkId::new("loop", size), batch, |b, batch| {
+            b.iter(|| {
+                for (proof, inputs) in batch {
+                    assert!(Groth16::<Bn254>::verify_proof(&pvk, proof, inputs).unwrap());
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batch", size), batch, |b, batch| {
+            b.iter(|| verify_batch(&pk.vk, batch, &mut rng).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
@@ -0,0 +1,636 @@
+//! Aggregation of many Groth16 proofs under one verifying key into a single
+//! proof of size logarithmic in their number, after SnarkPack (Gailly, Maller
+//! and Nitulescu, 2021).
+//!
+//! The prover commits to the proofs' `A`, `B` and `C` with pairing-based
+//! commitments and derives a random `r` from them. The Groth16 equations,
+//! weighted by `r^i`, then only involve `Z_AB = Π e(A_i, B_i)^{r^i}` and
+//! `Z_C = Σ r^i C_i`. An inner pairing product argument (TIPP) shows `Z_AB`
+//! is consistent with the commitment to `A` and `B`, and a multi-exponentiation
+//! argument (MIPP) does the same for `Z_C`. Both halve their vectors each
+//! round and end with KZG openings of the folded commitment keys.
+//!
+//! Counts that are not a power of two are padded by repeating the last proof
+//! and its public inputs.
+
+use std::ops::{Add, Mul};
+
+use ark_bn254::{Bn254, Fr as ArkFr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+
+use crate::ZkError;
+
+type Gt = PairingOutput<Bn254>;
+
+/// Structured reference string: powers of two secrets `a` and `b` in both
+/// groups.
+///
+/// `setup` samples `a` and `b` itself, so whoever runs it can forge
+/// aggregates. A production key must instead be taken from a powers-of-tau
+/// ceremony.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregationKey {
+    /// `g^{a^i}` and `g^{b^i}` for `i < 2n`.
+    g_a: Vec<G1Affine>,
+    g_b: Vec<G1Affine>,
+    /// `h^{a^i}` and `h^{b^i}` for `i < n`.
+    h_a: Vec<G2Affine>,
+    h_b: Vec<G2Affine>,
+}
+
+/// The part of an `AggregationKey` a verifier needs, independent of `n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregationVerifierKey {
+    g: G1Affine,
+    h: G2Affine,
+    g_a: G1Affine,
+    g_b: G1Affine,
+    h_a: G2Affine,
+    h_b: G2Affine,
+}
+
+impl AggregationKey {
+    /// A key for aggregating up to `max_proofs` proofs, rounded up to a power
+    /// of two.
+    pub fn setup<R: Rng>(max_proofs: usize, rng: &mut R) -> Self {
+        let n = max_proofs.max(1).next_power_of_two();
+        let (a, b) = (ArkFr::rand(rng), ArkFr::rand(rng));
+        AggregationKey {
+            g_a: powers::<G1Projective>(a, 2 * n),
+            g_b: powers::<G1Projective>(b, 2 * n),
+            h_a: powers::<G2Projective>(a, n),
+            h_b: powers::<G2Projective>(b, n),
+        }
+    }
+
+    pub fn max_proofs(&self) -> usize {
+        self.h_a.len()
+    }
+
+    pub fn verifier_key(&self) -> AggregationVerifierKey {
+        AggregationVerifierKey {
+            g: self.g_a[0],
+            h: self.h_a[0],
+            g_a: self.g_a[1],
+            g_b: self.g_b[1],
+            h_a: self.h_a[1],
+            h_b: self.h_b[1],
+        }
+    }
+}
+
+/// `generator^{x^i}` for `i < count`.
+fn powers<G: CurveGroup>(x: G::ScalarField, count: usize) -> Vec<G::Affine> {
+    let generator = G::generator();
+    let mut power = G::ScalarField::one();
+    let points: Vec<G> = (0..count)
+        .map(|_| {
+            let point = generator * power;
+            power *= x;
+            point
+        })
+        .collect();
+    G::normalize_batch(&points)
+}
+
+/// Pairing commitment under the two keys `a` and `b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Commitment {
+    t: Gt,
+    u: Gt,
+}
+
+impl Commitment {
+    fn fold(self, left: Commitment, right: Commitment, x: ArkFr, x_inv: ArkFr) -> Self {
+        Commitment {
+            t: self.t + left.t * x + right.t * x_inv,
+            u: self.u + left.u * x + right.u * x_inv,
+        }
+    }
+}
+
+/// Cross terms of one halving round.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Round {
+    com_ab_l: Commitment,
+    com_ab_r: Commitment,
+    z_ab_l: Gt,
+    z_ab_r: Gt,
+    com_c_l: Commitment,
+    com_c_r: Commitment,
+    z_c_l: G1Affine,
+    z_c_r: G1Affine,
+}
+
+/// Aggregate of `n` Groth16 proofs: `log2(n)` rounds plus a constant number
+/// of group elements.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateProof {
+    com_ab: Commitment,
+    com_c: Commitment,
+    z_ab: Gt,
+    z_c: G1Affine,
+    rounds: Vec<Round>,
+    /// The vectors and commitment keys, folded down to one element.
+    a: G1Affine,
+    b: G2Affine,
+    c: G1Affine,
+    v_a: G2Affine,
+    v_b: G2Affine,
+    w_a: G1Affine,
+    w_b: G1Affine,
+    /// KZG openings showing the folded keys were derived from the SRS.
+    opening_v_a: G2Affine,
+    opening_v_b: G2Affine,
+    opening_w_a: G1Affine,
+    opening_w_b: G1Affine,
+}
+
+impl AggregateProof {
+    /// Number of proofs after padding.
+    pub fn num_proofs(&self) -> usize {
+        1 << self.rounds.len()
+    }
+}
+
+/// Fiat-Shamir transcript over SHA-256.
+struct Transcript(Sha256);
+
+impl Transcript {
+    fn new(vk: &VerifyingKey<Bn254>, public_inputs: &[Vec<ArkFr>]) -> Result<Self, ZkError> {
+        let mut transcript = Transcript(Sha256::new_with_prefix(b"zk_snarks/snarkpack/v1"));
+        transcript.append(vk)?;
+        transcript.append(&public_inputs.to_vec())?;
+        Ok(transcript)
+    }
+
+    fn append<T: CanonicalSerialize>(&mut self, item: &T) -> Result<(), ZkError> {
+        let mut bytes = Vec::new();
+        item.serialize_uncompressed(&mut bytes)?;
+        self.0.update(bytes);
+        Ok(())
+    }
+
+    /// A non-zero challenge; the digest is fed back, so successive calls
+    /// differ.
+    fn challenge(&mut self) -> ArkFr {
+        loop {
+            let digest = self.0.clone().finalize();
+            self.0.update(digest);
+            let x = ArkFr::from_le_bytes_mod_order(&digest);
+            if !x.is_zero() {
+                return x;
+            }
+        }
+    }
+}
+
+/// Pads `items` to a power of two by repeating the last one.
+fn pad<T: Clone>(items: &[T]) -> Vec<T> {
+    let mut padded = items.to_vec();
+    if let Some(last) = items.last() {
+        padded.resize(items.len().next_power_of_two(), last.clone());
+    }
+    padded
+}
+
+/// `1, x, x^2, ...`, `count` of them.
+fn scalar_powers(x: ArkFr, count: usize) -> Vec<ArkFr> {
+    std::iter::successors(Some(ArkFr::one()), |power| Some(*power * x))
+        .take(count)
+        .collect()
+}
+
+fn fold<G>(left: &[G], right: &[G], x: ArkFr) -> Vec<G>
+where
+    G: Copy + Add<Output = G> + Mul<ArkFr, Output = G>,
+{
+    left.iter().zip(right).map(|(l, r)| *l + *r * x).collect()
+}
+
+fn inner_pairing(g1: &[G1Projective], g2: &[G2Projective]) -> Gt {
+    Bn254::multi_pairing(g1.iter().copied(), g2.iter().copied())
+}
+
+/// `Σ <a, v> + <w, b>`, the pairing commitment to `a` and `b`.
+fn pair_commit(
+    a: &[G1Projective],
+    v: &[G2Projective],
+    w: &[G1Projective],
+    b: &[G2Projective],
+) -> Gt {
+    Bn254::multi_pairing(a.iter().chain(w).copied(), v.iter().chain(b).copied())
+}
+
+fn msm(bases: &[G1Projective], scalars: &[ArkFr]) -> G1Projective {
+    G1Projective::msm_unchecked(&G1Projective::normalize_batch(bases), scalars)
+}
+
+/// Coefficients of `Π_k (1 + y_k X^{n / 2^(k+1)})` for `n = 2^ys.len()`.
+fn fold_polynomial(ys: &[ArkFr]) -> Vec<ArkFr> {
+    let mut coefficients = vec![ArkFr::one()];
+    for y in ys.iter().rev() {
+        let upper: Vec<ArkFr> = coefficients.iter().map(|c| *c * y).collect();
+        coefficients.extend(upper);
+    }
+    coefficients
+}
+
+/// The same polynomial as `fold_polynomial`, evaluated at `z`.
+fn evaluate_fold_polynomial(ys: &[ArkFr], z: ArkFr) -> ArkFr {
+    let mut product = ArkFr::one();
+    let mut z_power = z;
+    for y in ys.iter().rev() {
+        product *= ArkFr::one() + *y * z_power;
+        z_power.square_in_place();
+    }
+    product
+}
+
+/// Coefficients of `(p(X) - p(z)) / (X - z)`.
+fn kzg_quotient(p: &[ArkFr], z: ArkFr) -> Vec<ArkFr> {
+    let mut quotient = vec![ArkFr::zero(); p.len().saturating_sub(1)];
+    for i in (1..p.len()).rev() {
+        let carry = quotient.get(i).copied().unwrap_or_default();
+        quotient[i - 1] = p[i] + z * carry;
+    }
+    quotient
+}
+
+/// `Σ_i r^i L_i`, where `L_i` is proof `i`'s public-input term.
+fn weighted_inputs(
+    vk: &VerifyingKey<Bn254>,
+    public_inputs: &[Vec<ArkFr>],
+    r_powers: &[ArkFr],
+) -> G1Projective {
+    let mut scalars = vec![r_powers.iter().sum::<ArkFr>()];
+    for j in 0..vk.gamma_abc_g1.len() - 1 {
+        scalars.push(
+            public_inputs
+                .iter()
+                .zip(r_powers)
+                .map(|(inputs, r)| inputs[j] * r)
+                .sum(),
+        );
+    }
+    G1Projective::msm_unchecked(&vk.gamma_abc_g1, &scalars)
+}
+
+fn check_inputs(
+    vk: &VerifyingKey<Bn254>,
+    count: usize,
+    public_inputs: &[Vec<ArkFr>],
+    max_proofs: Option<usize>,
+) -> Result<(), ZkError> {
+    let invalid = |reason: String| ZkError::InvalidInput {
+        field: "proofs",
+        reason,
+    };
+    if count == 0 {
+        return Err(invalid("nothing to aggregate".to_string()));
+    }
+    if let Some(max) = max_proofs.filter(|max| count.next_power_of_two() > *max) {
+        return Err(invalid(format!(
+            "{} proofs exceed the key's {}",
+            count, max
+        )));
+    }
+    if public_inputs.len() != count {
+        return Err(invalid(format!(
+            "{} proofs but {} sets of public inputs",
+            count,
+            public_inputs.len()
+        )));
+    }
+    if let Some(inputs) = public_inputs
+        .iter()
+        .find(|inputs| inputs.len() + 1 != vk.gamma_abc_g1.len())
+    {
+        return Err(invalid(format!(
+            "{} public inputs where the key takes {}",
+            inputs.len(),
+            vk.gamma_abc_g1.len() - 1
+        )));
+    }
+    Ok(())
+}
+
+/// Aggregates proofs that each verify under `vk` against the matching
+/// `public_inputs`. Invalid proofs yield an aggregate that does not verify.
+#[instrument(name = "aggregation", skip_all, fields(proofs = proofs.len()))]
+pub fn aggregate_proofs(
+    key: &AggregationKey,
+    vk: &VerifyingKey<Bn254>,
+    proofs: &[Proof<Bn254>],
+    public_inputs: &[Vec<ArkFr>],
+) -> Result<AggregateProof, ZkError> {
+    check_inputs(vk, proofs.len(), public_inputs, Some(key.max_proofs()))?;
+    let proofs = pad(proofs);
+    let public_inputs = pad(public_inputs);
+    let n = proofs.len();
+
+    let mut a: Vec<G1Projective> = proofs.iter().map(|p| p.a.into_group()).collect();
+    let mut b: Vec<G2Projective> = proofs.iter().map(|p| p.b.into_group()).collect();
+    let mut c: Vec<G1Projective> = proofs.iter().map(|p| p.c.into_group()).collect();
+    let mut v_a: Vec<G2Projective> = key.h_a[..n].iter().map(|h| h.into_group()).collect();
+    let mut v_b: Vec<G2Projective> = key.h_b[..n].iter().map(|h| h.into_group()).collect();
+    let w_a: Vec<G1Projective> = key.g_a[n..2 * n].iter().map(|g| g.into_group()).collect();
+    let w_b: Vec<G1Projective> = key.g_b[n..2 * n].iter().map(|g| g.into_group()).collect();
+
+    let mut transcript = Transcript::new(vk, &public_inputs)?;
+    let com_ab = Commitment {
+        t: pair_commit(&a, &v_a, &w_a, &b),
+        u: pair_commit(&a, &v_b, &w_b, &b),
+    };
+    let com_c = Commitment {
+        t: inner_pairing(&c, &v_a),
+        u: inner_pairing(&c, &v_b),
+    };
+    transcript.append(&com_ab)?;
+    transcript.append(&com_c)?;
+    let r = transcript.challenge();
+    let r_inv = r.inverse().expect("challenges are non-zero");
+
+    // `Z_AB = <A, B^r>`: `B` is rescaled by `r^i`, and the keys paired with
+    // it by `r^-i`, which leaves `com_ab` unchanged.
+    let mut r_vec = scalar_powers(r, n);
+    b.iter_mut().zip(&r_vec).for_each(|(b, r)| *b *= r);
+    let r_inv_powers = scalar_powers(r_inv, n);
+    let rescale = |w: Vec<G1Projective>| -> Vec<G1Projective> {
+        w.iter().zip(&r_inv_powers).map(|(w, r)| *w * r).collect()
+    };
+    let (mut w_a, mut w_b) = (rescale(w_a), rescale(w_b));
+
+    let z_ab = inner_pairing(&a, &b);
+    let z_c = msm(&c, &r_vec);
+    transcript.append(&z_ab)?;
+    transcript.append(&z_c.into_affine())?;
+
+    let mut rounds = Vec::new();
+    let mut challenges = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (r_l, r_r) = r_vec.split_at(half);
+        let (v_a_l, v_a_r) = v_a.split_at(half);
+        let (v_b_l, v_b_r) = v_b.split_at(half);
+        let (w_a_l, w_a_r) = w_a.split_at(half);
+        let (w_b_l, w_b_r) = w_b.split_at(half);
+
+        let round = Round {
+            com_ab_l: Commitment {
+                t: pair_commit(a_r, v_a_l, w_a_r, b_l),
+                u: pair_commit(a_r, v_b_l, w_b_r, b_l),
+            },
+            com_ab_r: Commitment {
+                t: pair_commit(a_l, v_a_r, w_a_l, b_r),
+                u: pair_commit(a_l, v_b_r, w_b_l, b_r),
+            },
+            z_ab_l: inner_pairing(a_r, b_l),
+            z_ab_r: inner_pairing(a_l, b_r),
+            com_c_l: Commitment {
+                t: inner_pairing(c_r, v_a_l),
+                u: inner_pairing(c_r, v_b_l),
+            },
+            com_c_r: Commitment {
+                t: inner_pairing(c_l, v_a_r),
+                u: inner_pairing(c_l, v_b_r),
+            },
+            z_c_l: msm(c_r, r_l).into_affine(),
+            z_c_r: msm(c_l, r_r).into_affine(),
+        };
+        transcript.append(&round)?;
+        let x = transcript.challenge();
+        let x_inv = x.inverse().expect("challenges are non-zero");
+
+        a = fold(a_l, a_r, x);
+        b = fold(b_l, b_r, x_inv);
+        c = fold(c_l, c_r, x);
+        r_vec = fold(r_l, r_r, x_inv);
+        v_a = fold(v_a_l, v_a_r, x_inv);
+        v_b = fold(v_b_l, v_b_r, x_inv);
+        w_a = fold(w_a_l, w_a_r, x);
+        w_b = fold(w_b_l, w_b_r, x);
+        rounds.push(round);
+        challenges.push(x);
+    }
+
+    let mut proof = AggregateProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c: z_c.into_affine(),
+        rounds,
+        a: a[0].into_affine(),
+        b: b[0].into_affine(),
+        c: c[0].into_affine(),
+        v_a: v_a[0].into_affine(),
+        v_b: v_b[0].into_affine(),
+        w_a: w_a[0].into_affine(),
+        w_b: w_b[0].into_affine(),
+        opening_v_a: G2Affine::zero(),
+        opening_v_b: G2Affine::zero(),
+        opening_w_a: G1Affine::zero(),
+        opening_w_b: G1Affine::zero(),
+    };
+    let z = final_challenge(&mut transcript, &proof)?;
+
+    // `v* = h^{f_v(a)}` with `f_v = Π (1 + x_k^-1 X^(n/2^(k+1)))`.
+    let inverses: Vec<ArkFr> = challenges.iter().map(|x| x.inverse().unwrap()).collect();
+    let quotient = kzg_quotient(&fold_polynomial(&inverses), z);
+    proof.opening_v_a = G2Projective::msm_unchecked(&key.h_a, &quotient).into_affine();
+    proof.opening_v_b = G2Projective::msm_unchecked(&key.h_b, &quotient).into_affine();
+
+    // `w* = g^{P(a)}` with `P = X^n f_w(X / r)`, `f_w = Π (1 + x_k X^(n/2^(k+1)))`.
+    let mut p = vec![ArkFr::zero(); n];
+    p.extend(
+        fold_polynomial(&challenges)
+            .iter()
+            .zip(&r_inv_powers)
+            .map(|(c, r)| *c * r),
+    );
+    let quotient = kzg_quotient(&p, z);
+    proof.opening_w_a = G1Projective::msm_unchecked(&key.g_a, &quotient).into_affine();
+    proof.opening_w_b = G1Projective::msm_unchecked(&key.g_b, &quotient).into_affine();
+
+    debug!(rounds = proof.rounds.len(), "aggregated");
+    Ok(proof)
+}
+
+/// The KZG evaluation point, drawn after the folded elements.
+fn final_challenge(transcript: &mut Transcript, proof: &AggregateProof) -> Result<ArkFr, ZkError> {
+    transcript.append(&(proof.a, proof.b, proof.c))?;
+    transcript.append(&(proof.v_a, proof.v_b, proof.w_a, proof.w_b))?;
+    Ok(transcript.challenge())
+}
+
+/// Checks an aggregate against the public inputs of every proof in it, in
+/// the order they were aggregated. `Ok(false)` for a well-formed aggregate
+/// that does not verify.
+#[instrument(name = "verification", skip_all, fields(proofs = public_inputs.len()))]
+pub fn verify_aggregate(
+    key: &AggregationVerifierKey,
+    vk: &VerifyingKey<Bn254>,
+    public_inputs: &[Vec<ArkFr>],
+    proof: &AggregateProof,
+) -> Result<bool, ZkError> {
+    check_inputs(vk, public_inputs.len(), public_inputs, None)?;
+    let public_inputs = pad(public_inputs);
+    let n = public_inputs.len();
+    if proof.rounds.len() != n.trailing_zeros() as usize {
+        debug!(
+            expected = n,
+            rounds = proof.rounds.len(),
+            "wrong aggregate size"
+        );
+        return Ok(false);
+    }
+
+    let mut transcript = Transcript::new(vk, &public_inputs)?;
+    transcript.append(&proof.com_ab)?;
+    transcript.append(&proof.com_c)?;
+    let r = transcript.challenge();
+    transcript.append(&proof.z_ab)?;
+    transcript.append(&proof.z_c)?;
+
+    // The Groth16 equations, weighted by `r^i` and multiplied together.
+    let r_powers = scalar_powers(r, n);
+    let r_sum: ArkFr = r_powers.iter().sum();
+    let groth16 = Bn254::multi_pairing(
+        [
+            vk.alpha_g1 * r_sum,
+            weighted_inputs(vk, &public_inputs, &r_powers),
+            proof.z_c.into_group(),
+        ],
+        [vk.beta_g2, vk.gamma_g2, vk.delta_g2],
+    );
+    if groth16 != proof.z_ab {
+        debug!("weighted Groth16 equation does not hold");
+        return Ok(false);
+    }
+
+    let (mut com_ab, mut com_c) = (proof.com_ab, proof.com_c);
+    let (mut z_ab, mut z_c) = (proof.z_ab, proof.z_c.into_group());
+    let mut challenges = Vec::new();
+    for round in &proof.rounds {
+        transcript.append(round)?;
+        let x = transcript.challenge();
+        let x_inv = x.inverse().expect("challenges are non-zero");
+        com_ab = com_ab.fold(round.com_ab_l, round.com_ab_r, x, x_inv);
+        com_c = com_c.fold(round.com_c_l, round.com_c_r, x, x_inv);
+        z_ab += round.z_ab_l * x + round.z_ab_r * x_inv;
+        z_c += round.z_c_l * x + round.z_c_r * x_inv;
+        challenges.push(x);
+    }
+    let z = final_challenge(&mut transcript, proof)?;
+
+    let inverses: Vec<ArkFr> = challenges.iter().map(|x| x.inverse().unwrap()).collect();
+    let r_star = evaluate_fold_polynomial(&inverses, r);
+    let folded = com_ab.t == Bn254::multi_pairing([proof.a, proof.w_a], [proof.v_a, proof.b])
+        && com_ab.u == Bn254::multi_pairing([proof.a, proof.w_b], [proof.v_b, proof.b])
+        && z_ab == Bn254::pairing(proof.a, proof.b)
+        && com_c.t == Bn254::pairing(proof.c, proof.v_a)
+        && com_c.u == Bn254::pairing(proof.c, proof.v_b)
+        && z_c == proof.c * r_star;
+    if !folded {
+        debug!("folded commitments do not open");
+        return Ok(false);
+    }
+
+    // KZG: `e(g, v* - f_v(z) h) = e(g^s - z g, π)` for `s` in `a` and `b`,
+    // and `e(w* - P(z) g, h) = e(π, h^s - z h)`.
+    let f_v = evaluate_fold_polynomial(&inverses, z);
+    let f_w = z.pow([n as u64]) * evaluate_fold_polynomial(&challenges, z * r.inverse().unwrap());
+    let g = key.g.into_group();
+    let h = key.h.into_group();
+    let opens_v = |v: G2Affine, g_s: G1Affine, opening: G2Affine| {
+        Bn254::multi_pairing(
+            [g, g * z - g_s],
+            [v.into_group() - h * f_v, opening.into_group()],
+        )
+        .is_zero()
+    };
+    let opens_w = |w: G1Affine, h_s: G2Affine, opening: G1Affine| {
+        Bn254::multi_pairing(
+            [w.into_group() - g * f_w, -opening.into_group()],
+            [h, h_s.into_group() - h * z],
+        )
+        .is_zero()
+    };
+    Ok(opens_v(proof.v_a, key.g_a, proof.opening_v_a)
+        && opens_v(proof.v_b, key.g_b, proof.opening_v_b)
+        && opens_w(proof.w_a, key.h_a, proof.opening_w_a)
+        && opens_w(proof.w_b, key.h_b, proof.opening_w_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_proof, generate_proving_key, public_inputs, MerkleTreeData};
+    use ark_std::test_rng;
+
+    fn proofs(count: u64) -> (VerifyingKey<Bn254>, Vec<Proof<Bn254>>, Vec<Vec<ArkFr>>) {
+        let mut rng = test_rng();
+        let pk = generate_proving_key(1, &mut rng).unwrap();
+        let (proofs, inputs) = (0..count)
+            .map(|i| {
+                let data = MerkleTreeData {
+                    leaf: ArkFr::from(i),
+                    siblings: vec![ArkFr::from(100 + i)],
+                    path_bits: vec![i % 2 == 0],
+                };
+                let proof = create_proof(&pk, &data, &mut rng).unwrap();
+                (proof, public_inputs(&data).unwrap())
+            })
+            .unzip();
+        (pk.vk, proofs, inputs)
+    }
+
+    #[test]
+    fn test_aggregate_verifies() {
+        let key = AggregationKey::setup(8, &mut test_rng());
+        for count in [1, 4, 5] {
+            let (vk, proofs, inputs) = proofs(count);
+            let aggregate = aggregate_proofs(&key, &vk, &proofs, &inputs).unwrap();
+            assert_eq!(aggregate.num_proofs(), (count as usize).next_power_of_two());
+            assert!(verify_aggregate(&key.verifier_key(), &vk, &inputs, &aggregate).unwrap());
+
+            let mut bytes = Vec::new();
+            aggregate.serialize_compressed(&mut bytes).unwrap();
+            let decoded = AggregateProof::deserialize_compressed(&bytes[..]).unwrap();
+            assert_eq!(decoded, aggregate);
+        }
+    }
+
+    #[test]
+    fn test_rejects_altered_statement() {
+        let key = AggregationKey::setup(4, &mut test_rng());
+        let verifier_key = key.verifier_key();
+        let (vk, mut proofs, mut inputs) = proofs(4);
+        let aggregate = aggregate_proofs(&key, &vk, &proofs, &inputs).unwrap();
+
+        let mut swapped = inputs.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_aggregate(&verifier_key, &vk, &swapped, &aggregate).unwrap());
+        assert!(!verify_aggregate(&verifier_key, &vk, &inputs[..2], &aggregate).unwrap());
+
+        // A proof for other inputs makes the aggregate fail, not the prover.
+        proofs[2] = proofs[3].clone();
+        let aggregate = aggregate_proofs(&key, &vk, &proofs, &inputs).unwrap();
+        assert!(!verify_aggregate(&verifier_key, &vk, &inputs, &aggregate).unwrap());
+
+        inputs.push(inputs[0].clone());
+        proofs.push(proofs[0].clone());
+        let err = aggregate_proofs(&key, &vk, &proofs, &inputs).unwrap_err();
+        assert_eq!(err.code(), 14);
+    }
+}
@@ -0,0 +1,127 @@
+//! Verification of many Groth16 proofs under one verifying key with a single
+//! multi-pairing.
+//!
+//! Each proof's equation `e(A, B) = e(α, β) · e(L, γ) · e(C, δ)` is scaled
+//! by a random 128-bit `r` and the equations are multiplied together, so the
+//! batch costs one Miller loop per proof plus three, and one final
+//! exponentiation. A batch containing a bad proof passes with probability
+//! about 2^-128.
+
+use ark_bn254::{Bn254, Fr as ArkFr, G1Projective, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_std::rand::Rng;
+use ark_std::Zero;
+use tracing::{debug, instrument};
+
+use crate::ZkError;
+
+/// A proof and the public inputs it is checked against.
+pub type BatchItem = (Proof<Bn254>, Vec<ArkFr>);
+
+/// Checks every `(proof, public_inputs)` pair against `vk` and returns one
+/// flag per pair, in order. If the combined check fails, the proofs are
+/// verified one by one to find the bad ones. A pair whose number of public
+/// inputs does not fit the key is `false`.
+#[instrument(name = "verification", skip_all, fields(proofs = items.len()))]
+pub fn verify_batch<R: Rng>(
+    vk: &VerifyingKey<Bn254>,
+    items: &[BatchItem],
+    rng: &mut R,
+) -> Result<Vec<bool>, ZkError> {
+    let pvk = ark_groth16::prepare_verifying_key(vk);
+    let well_formed: Vec<usize> = (0..items.len())
+        .filter(|&i| items[i].1.len() + 1 == vk.gamma_abc_g1.len())
+        .collect();
+
+    let mut valid = vec![false; items.len()];
+    if combined_check(&pvk, well_formed.iter().map(|&i| &items[i]), rng)? {
+        for i in well_formed {
+            valid[i] = true;
+        }
+        return Ok(valid);
+    }
+
+    debug!("combined check failed, verifying proofs one by one");
+    for i in well_formed {
+        let (proof, public_inputs) = &items[i];
+        valid[i] = Groth16::<Bn254>::verify_proof(&pvk, proof, public_inputs)
+            .map_err(ZkError::Verification)?;
+    }
+    Ok(valid)
+}
+
+/// Whether `Π e(rA, B) · e(-Σ rα, β) · e(-Σ rL, γ) · e(-Σ rC, δ)` is one.
+fn combined_check<'a, R: Rng>(
+    pvk: &PreparedVerifyingKey<Bn254>,
+    items: impl Iterator<Item = &'a BatchItem>,
+    rng: &mut R,
+) -> Result<bool, ZkError> {
+    let mut g1: Vec<G1Projective> = Vec::new();
+    let mut g2: Vec<G2Affine> = Vec::new();
+    let mut r_sum = ArkFr::zero();
+    let mut inputs_sum = G1Projective::zero();
+    let mut c_sum = G1Projective::zero();
+
+    for (proof, public_inputs) in items {
+        let r = ArkFr::from(rng.gen::<u128>());
+        let prepared_inputs =
+            Groth16::<Bn254>::prepare_inputs(pvk, public_inputs).map_err(ZkError::Verification)?;
+        g1.push(proof.a * r);
+        g2.push(proof.b);
+        r_sum += r;
+        inputs_sum += prepared_inputs * r;
+        c_sum += proof.c * r;
+    }
+    if g1.is_empty() {
+        return Ok(true);
+    }
+
+    let vk = &pvk.vk;
+    g1.extend([vk.alpha_g1 * -r_sum, -inputs_sum, -c_sum]);
+    g2.extend([vk.beta_g2, vk.gamma_g2, vk.delta_g2]);
+    Ok(Bn254::multi_pairing(g1, g2).is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_proof, generate_proving_key, public_inputs, MerkleTreeData};
+    use ark_std::test_rng;
+
+    fn batch(size: u64) -> (VerifyingKey<Bn254>, Vec<BatchItem>) {
+        let mut rng = test_rng();
+        let pk = generate_proving_key(1, &mut rng).unwrap();
+        let items = (0..size)
+            .map(|i| {
+                let data = MerkleTreeData {
+                    leaf: ArkFr::from(i),
+                    siblings: vec![ArkFr::from(100 + i)],
+                    path_bits: vec![i % 2 == 0],
+                };
+                let proof = create_proof(&pk, &data, &mut rng).unwrap();
+                (proof, public_inputs(&data).unwrap())
+            })
+            .collect();
+        (pk.vk, items)
+    }
+
+    #[test]
+    fn test_accepts_valid_batch() {
+        let (vk, items) = batch(4);
+        let valid = verify_batch(&vk, &items, &mut test_rng()).unwrap();
+        assert_eq!(valid, vec![true; 4]);
+        assert!(verify_batch(&vk, &[], &mut test_rng()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_identifies_bad_proofs() {
+        let (vk, mut items) = batch(4);
+        // Proof 1 checked against proof 2's root, proof 3 with an extra input.
+        items[1].1 = items[2].1.clone();
+        items[3].1.push(ArkFr::from(1u64));
+
+        let valid = verify_batch(&vk, &items, &mut test_rng()).unwrap();
+        assert_eq!(valid, vec![true, false, true, false]);
+    }
+}
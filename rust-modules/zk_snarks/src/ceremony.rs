@@ -0,0 +1,506 @@
+//! Phase-2 trusted setup for a circuit's Groth16 key, after Bowe, Gabizon
+//! and Miers (2017).
+//!
+//! The initial key is derived from a phase-1 `PowersOfTau` transcript with
+//! `γ = δ = 1`, so no one learns `τ`, `α` or `β` unless every phase-1
+//! participant kept their secrets. In phase 2 each participant multiplies `δ`
+//! by a secret of their own and publishes a proof of knowledge of it.
+//! `Ceremony::verify` re-derives the initial key from the circuit and the
+//! phase-1 transcript and checks every contribution since, so a verified key
+//! can only be forged by someone who holds the secrets of every participant
+//! of one phase.
+//!
+//! A ceremony is passed from participant to participant as its
+//! `CanonicalSerialize` encoding.
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr as ArkFr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, PrimeField, UniformRand, Zero};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument};
+
+use crate::powers_of_tau::PowersOfTau;
+use crate::ZkError;
+
+/// Proof of knowledge of a secret factor `x`: a random `s`, `s^x`, and
+/// `r^x`, where `r` is hashed from the transcript so far and `s`, `s^x`.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KnowledgeProof {
+    pub s: G1Affine,
+    pub s_x: G1Affine,
+    pub r_x: G2Affine,
+}
+
+impl KnowledgeProof {
+    pub(crate) fn new<R: Rng>(
+        transcript: &[u8; 32],
+        x: ArkFr,
+        rng: &mut R,
+    ) -> Result<Self, ZkError> {
+        let s = G1Projective::rand(rng).into_affine();
+        let s_x = (s * x).into_affine();
+        let r = hash_to_g2(transcript, &s, &s_x)?;
+        Ok(KnowledgeProof {
+            s,
+            s_x,
+            r_x: (r * x).into_affine(),
+        })
+    }
+
+    /// `(r, r^x)` if the proof shows knowledge of a non-zero `x`, for the
+    /// caller to check its update against.
+    pub(crate) fn ratio(
+        &self,
+        transcript: &[u8; 32],
+    ) -> Result<Option<(G2Affine, G2Affine)>, ZkError> {
+        let r = hash_to_g2(transcript, &self.s, &self.s_x)?;
+        let knows_x = !self.s.is_zero()
+            && !self.s_x.is_zero()
+            && same_ratio((self.s, self.s_x), (r, self.r_x));
+        Ok(knows_x.then_some((r, self.r_x)))
+    }
+}
+
+/// One participant's public record: the new `δ` in G1 and a proof that they
+/// know the factor it was multiplied by.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution {
+    pub delta_after: G1Affine,
+    pub proof: KnowledgeProof,
+}
+
+/// The initial key, every contribution so far and the key they produced.
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Ceremony {
+    pub circuit_id: String,
+    initial: ProvingKey<Bn254>,
+    contributions: Vec<Contribution>,
+    current: ProvingKey<Bn254>,
+}
+
+fn invalid(reason: String) -> ZkError {
+    ZkError::InvalidInput {
+        field: "ceremony",
+        reason,
+    }
+}
+
+impl Ceremony {
+    /// Starts a ceremony from the key of `circuit` derived from `phase1`.
+    /// The transcript is not checked here; `verify` checks it.
+    #[instrument(name = "setup", skip(circuit, phase1))]
+    pub fn new<C>(circuit_id: &str, circuit: C, phase1: &PowersOfTau) -> Result<Self, ZkError>
+    where
+        C: ConstraintSynthesizer<ArkFr>,
+    {
+        let initial = initial_key(circuit, phase1)?;
+        Ok(Ceremony {
+            circuit_id: circuit_id.to_string(),
+            current: initial.clone(),
+            initial,
+            contributions: Vec::new(),
+        })
+    }
+
+    pub fn contributions(&self) -> &[Contribution] {
+        &self.contributions
+    }
+
+    /// Hash of the ceremony after `count` contributions; with `count = 0`,
+    /// of the initial key.
+    fn hash_after(&self, count: usize) -> Result<[u8; 32], ZkError> {
+        let mut bytes = Vec::new();
+        self.circuit_id.serialize_uncompressed(&mut bytes)?;
+        self.initial.serialize_uncompressed(&mut bytes)?;
+        let mut hash: [u8; 32] = Sha256::digest(&bytes).into();
+
+        for contribution in &self.contributions[..count] {
+            let mut bytes = hash.to_vec();
+            contribution.serialize_uncompressed(&mut bytes)?;
+            hash = Sha256::digest(&bytes).into();
+        }
+        Ok(hash)
+    }
+
+    /// Multiplies `δ` by a fresh secret, which is dropped on return, and
+    /// returns the contribution hash for the participant to publish.
+    #[instrument(name = "contribution", skip_all, fields(index = self.contributions.len()))]
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> Result<[u8; 32], ZkError> {
+        let x = ArkFr::rand(rng);
+        let x_inv = x
+            .inverse()
+            .ok_or_else(|| invalid("zero secret".to_string()))?;
+        let proof = KnowledgeProof::new(&self.hash_after(self.contributions.len())?, x, rng)?;
+
+        let key = &mut self.current;
+        key.delta_g1 = (key.delta_g1 * x).into_affine();
+        key.vk.delta_g2 = (key.vk.delta_g2 * x).into_affine();
+        key.h_query = scale(&key.h_query, x_inv);
+        key.l_query = scale(&key.l_query, x_inv);
+
+        self.contributions.push(Contribution {
+            delta_after: key.delta_g1,
+            proof,
+        });
+        let hash = self.hash_after(self.contributions.len())?;
+        info!(hash = %hex::encode(hash), "contributed");
+        Ok(hash)
+    }
+
+    /// Checks `phase1`, that the initial key is the one `circuit` derives
+    /// from it, every contribution, and that the current key follows from the
+    /// initial one by them alone. Returns the contribution hashes in order,
+    /// so each participant can find the one they published.
+    #[instrument(name = "verification", skip_all, fields(contributions = self.contributions.len()))]
+    pub fn verify<C, R>(
+        &self,
+        circuit: C,
+        phase1: &PowersOfTau,
+        rng: &mut R,
+    ) -> Result<Vec<[u8; 32]>, ZkError>
+    where
+        C: ConstraintSynthesizer<ArkFr>,
+        R: Rng,
+    {
+        phase1.verify(rng)?;
+        if initial_key(circuit, phase1)? != self.initial {
+            return Err(invalid(
+                "the initial key is not derived from the circuit and phase 1".to_string(),
+            ));
+        }
+
+        let mut hashes = Vec::new();
+        let mut delta = self.initial.delta_g1;
+        for (i, contribution) in self.contributions.iter().enumerate() {
+            let updated = contribution
+                .proof
+                .ratio(&self.hash_after(i)?)?
+                .is_some_and(|ratio| same_ratio((delta, contribution.delta_after), ratio));
+            if !updated {
+                return Err(invalid(format!("contribution {} does not update δ", i)));
+            }
+            delta = contribution.delta_after;
+            hashes.push(self.hash_after(i + 1)?);
+        }
+
+        let (initial, current) = (&self.initial, &self.current);
+        let delta_g1 = (initial.delta_g1, delta);
+        let delta_g2 = (initial.vk.delta_g2, current.vk.delta_g2);
+        if current.delta_g1 != delta || !same_ratio(delta_g1, delta_g2) {
+            return Err(invalid(
+                "δ of the key differs from the last contribution".to_string(),
+            ));
+        }
+        let unchanged = ProvingKey {
+            vk: VerifyingKey {
+                delta_g2: initial.vk.delta_g2,
+                ..current.vk.clone()
+            },
+            delta_g1: initial.delta_g1,
+            h_query: initial.h_query.clone(),
+            l_query: initial.l_query.clone(),
+            ..current.clone()
+        };
+        if unchanged != *initial {
+            return Err(invalid("the key changed beyond δ".to_string()));
+        }
+
+        // `H` and `L` must have been divided by exactly what `δ` was
+        // multiplied by: checked on a random combination of each.
+        for (before, after) in [
+            (&initial.h_query, &current.h_query),
+            (&initial.l_query, &current.l_query),
+        ] {
+            if before.len() != after.len() {
+                return Err(invalid("the key changed beyond δ".to_string()));
+            }
+            let weights: Vec<ArkFr> = (0..before.len()).map(|_| ArkFr::rand(rng)).collect();
+            let before = G1Projective::msm_unchecked(before, &weights).into_affine();
+            let after = G1Projective::msm_unchecked(after, &weights).into_affine();
+            if !same_ratio((after, before), (initial.vk.delta_g2, current.vk.delta_g2)) {
+                return Err(invalid("H or L does not match δ".to_string()));
+            }
+        }
+
+        debug!("ceremony verified");
+        Ok(hashes)
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.current
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.current.vk
+    }
+
+    /// The final proving key, uncompressed, as `myService` reads it from its
+    /// keys directory.
+    pub fn export_proving_key(&self) -> Result<Vec<u8>, ZkError> {
+        let mut bytes = Vec::new();
+        self.current.serialize_uncompressed(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+fn scale(points: &[G1Affine], factor: ArkFr) -> Vec<G1Affine> {
+    let scaled: Vec<G1Projective> = points.iter().map(|point| *point * factor).collect();
+    G1Projective::normalize_batch(&scaled)
+}
+
+/// The Groth16 key of `circuit` for phase 1's `τ`, `α` and `β`, with
+/// `γ = δ = 1`: the key `Groth16::generate_parameters_with_qap` makes with
+/// the libsnark reduction, computed from the transcript instead of the
+/// secrets.
+fn initial_key<C: ConstraintSynthesizer<ArkFr>>(
+    circuit: C,
+    phase1: &PowersOfTau,
+) -> Result<ProvingKey<Bn254>, ZkError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let matrices = cs
+        .to_matrices()
+        .ok_or_else(|| invalid("the circuit has no constraint matrices".to_string()))?;
+
+    let num_constraints = cs.num_constraints();
+    let num_instance = cs.num_instance_variables();
+    let num_variables = num_instance + cs.num_witness_variables();
+    let domain = GeneralEvaluationDomain::<ArkFr>::new(num_constraints + num_instance)
+        .ok_or_else(|| invalid("the circuit is too large for the field".to_string()))?;
+    let basis = phase1.lagrange_basis(&domain)?;
+
+    // Polynomials `a`, `b` and `c` of each variable at `τ`; `abc` collects
+    // `β a + α b + c`. Each instance variable also gets a row of its own in
+    // `a`, after the constraints, which keeps the inputs independent.
+    let mut a = vec![G1Projective::zero(); num_variables];
+    let mut b_g1 = a.clone();
+    let mut b_g2 = vec![G2Projective::zero(); num_variables];
+    let mut abc = a.clone();
+    for i in 0..num_instance {
+        a[i] += basis.tau_g1[num_constraints + i];
+        abc[i] += basis.beta_tau_g1[num_constraints + i];
+    }
+    let rows = matrices.a.iter().zip(&matrices.b).zip(&matrices.c);
+    for (row, ((a_row, b_row), c_row)) in rows.enumerate() {
+        for (coeff, index) in a_row {
+            a[*index] += basis.tau_g1[row] * coeff;
+            abc[*index] += basis.beta_tau_g1[row] * coeff;
+        }
+        for (coeff, index) in b_row {
+            b_g1[*index] += basis.tau_g1[row] * coeff;
+            b_g2[*index] += basis.tau_g2[row] * coeff;
+            abc[*index] += basis.alpha_tau_g1[row] * coeff;
+        }
+        for (coeff, index) in c_row {
+            abc[*index] += basis.tau_g1[row] * coeff;
+        }
+    }
+
+    let abc = G1Projective::normalize_batch(&abc);
+    let (gamma_abc_g1, l_query) = abc.split_at(num_instance);
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: phase1.alpha_g1(),
+            beta_g2: phase1.beta_g2(),
+            gamma_g2: G2Affine::generator(),
+            delta_g2: G2Affine::generator(),
+            gamma_abc_g1: gamma_abc_g1.to_vec(),
+        },
+        beta_g1: phase1.beta_g1(),
+        delta_g1: G1Affine::generator(),
+        a_query: G1Projective::normalize_batch(&a),
+        b_g1_query: G1Projective::normalize_batch(&b_g1),
+        b_g2_query: G2Projective::normalize_batch(&b_g2),
+        h_query: phase1.h_query(domain.size()),
+        l_query: l_query.to_vec(),
+    })
+}
+
+/// Whether `a.1 = a.0^x` and `b.1 = b.0^x` for the same `x`.
+pub(crate) fn same_ratio(a: (G1Affine, G1Affine), b: (G2Affine, G2Affine)) -> bool {
+    Bn254::pairing(a.0, b.1) == Bn254::pairing(a.1, b.0)
+}
+
+/// A G2 point with unknown discrete logarithm, by try-and-increment over
+/// SHA-256.
+fn hash_to_g2(transcript: &[u8; 32], s: &G1Affine, s_x: &G1Affine) -> Result<G2Affine, ZkError> {
+    let mut seed = transcript.to_vec();
+    s.serialize_uncompressed(&mut seed)?;
+    s_x.serialize_uncompressed(&mut seed)?;
+
+    for counter in 0u64.. {
+        let digest = |part: u8| {
+            let mut hasher = Sha256::new();
+            hasher.update(&seed);
+            hasher.update(counter.to_le_bytes());
+            hasher.update([part]);
+            hasher.finalize()
+        };
+        let (c0, c1) = (digest(0), digest(1));
+        let x = Fq2::new(
+            Fq::from_le_bytes_mod_order(&c0),
+            Fq::from_le_bytes_mod_order(&c1),
+        );
+        if let Some(point) = G2Affine::get_point_from_x_unchecked(x, c0[31] & 1 == 1) {
+            let point = point.clear_cofactor();
+            if !point.is_zero() {
+                return Ok(point);
+            }
+        }
+    }
+    unreachable!("no point found in 2^64 attempts")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::Groth16;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+    use ark_std::rand::SeedableRng;
+    use ark_std::test_rng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// `x^3 + x + constant = out`, small enough for a phase 1 of size 8.
+    #[derive(Clone, Copy)]
+    struct Cubic {
+        x: u64,
+        constant: u64,
+    }
+
+    impl Cubic {
+        fn out(&self) -> ArkFr {
+            ArkFr::from(self.x.pow(3) + self.x + self.constant)
+        }
+    }
+
+    impl ConstraintSynthesizer<ArkFr> for Cubic {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<ArkFr>,
+        ) -> Result<(), SynthesisError> {
+            let out = FpVar::new_input(cs.clone(), || Ok(self.out()))?;
+            let x = FpVar::new_witness(cs, || Ok(ArkFr::from(self.x)))?;
+            let sum = &x * &x * &x + &x + FpVar::constant(ArkFr::from(self.constant));
+            sum.enforce_equal(&out)
+        }
+    }
+
+    const CIRCUIT: Cubic = Cubic { x: 3, constant: 5 };
+    const CIRCUIT_ID: &str = "cubic";
+
+    fn transcript(participants: u64) -> PowersOfTau {
+        let mut powers = PowersOfTau::new(8).unwrap();
+        for participant in 0..participants {
+            let mut rng = ChaCha20Rng::seed_from_u64(100 + participant);
+            powers.contribute(&mut rng).unwrap();
+        }
+        powers
+    }
+
+    #[test]
+    fn test_contributions_chain_and_verify() {
+        let phase1 = transcript(2);
+        let mut ceremony = Ceremony::new(CIRCUIT_ID, CIRCUIT, &phase1).unwrap();
+        let mut published = Vec::new();
+        for participant in 0..3 {
+            // Each participant receives the encoded ceremony and passes it on.
+            let mut bytes = Vec::new();
+            ceremony.serialize_compressed(&mut bytes).unwrap();
+            ceremony = Ceremony::deserialize_compressed(&bytes[..]).unwrap();
+            let mut rng = ChaCha20Rng::seed_from_u64(participant);
+            published.push(ceremony.contribute(&mut rng).unwrap());
+        }
+        let hashes = ceremony.verify(CIRCUIT, &phase1, &mut test_rng());
+        assert_eq!(hashes.unwrap(), published);
+        assert_ne!(ceremony.verifying_key(), &ceremony.initial.vk);
+
+        let pk = ceremony.proving_key();
+        let proof =
+            Groth16::<Bn254>::create_random_proof_with_reduction(CIRCUIT, pk, &mut test_rng())
+                .unwrap();
+        let pvk = ark_groth16::prepare_verifying_key(ceremony.verifying_key());
+        let verify = |out| Groth16::<Bn254>::verify_proof(&pvk, &proof, &[out]).unwrap();
+        assert!(verify(CIRCUIT.out()));
+        assert!(!verify(CIRCUIT.out() + ArkFr::from(1u64)));
+
+        let exported = ceremony.export_proving_key().unwrap();
+        let exported = ProvingKey::<Bn254>::deserialize_uncompressed(&exported[..]).unwrap();
+        assert_eq!(&exported, pk);
+    }
+
+    #[test]
+    fn test_rejects_initial_key_not_from_phase1() {
+        let phase1 = transcript(1);
+        let ceremony = Ceremony::new(CIRCUIT_ID, CIRCUIT, &phase1).unwrap();
+        assert!(ceremony.verify(CIRCUIT, &phase1, &mut test_rng()).is_ok());
+
+        // A key from a local setup, whose secrets the coordinator knows.
+        let mut forged = ceremony.clone();
+        forged.initial =
+            Groth16::<Bn254>::generate_random_parameters_with_reduction(CIRCUIT, &mut test_rng())
+                .unwrap();
+        forged.current = forged.initial.clone();
+        assert!(forged.verify(CIRCUIT, &phase1, &mut test_rng()).is_err());
+
+        // The key of another circuit, or from another transcript.
+        let other_circuit = Cubic { x: 3, constant: 6 };
+        assert!(ceremony
+            .verify(other_circuit, &phase1, &mut test_rng())
+            .is_err());
+        let other_phase1 = transcript(2);
+        assert!(ceremony
+            .verify(CIRCUIT, &other_phase1, &mut test_rng())
+            .is_err());
+        assert!(ceremony
+            .verify(CIRCUIT, &PowersOfTau::new(8).unwrap(), &mut test_rng())
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejects_tampered_ceremony() {
+        let phase1 = transcript(1);
+        let mut ceremony = Ceremony::new(CIRCUIT_ID, CIRCUIT, &phase1).unwrap();
+        ceremony.contribute(&mut test_rng()).unwrap();
+        ceremony.contribute(&mut test_rng()).unwrap();
+        let verify = |ceremony: &Ceremony| ceremony.verify(CIRCUIT, &phase1, &mut test_rng());
+
+        // δ replaced by one whose factor is known, without a matching proof.
+        let mut forged = ceremony.clone();
+        let delta = (forged.current.delta_g1 * ArkFr::from(2u64)).into_affine();
+        forged.current.delta_g1 = delta;
+        forged.contributions[1].delta_after = delta;
+        assert!(verify(&forged).is_err());
+
+        // A zero factor, which would make the key depend on δ alone.
+        let mut forged = ceremony.clone();
+        let proof = &mut forged.contributions[1].proof;
+        (proof.s_x, proof.r_x) = (G1Affine::zero(), G2Affine::zero());
+        forged.contributions[1].delta_after = G1Affine::zero();
+        assert!(verify(&forged).is_err());
+
+        let mut forged = ceremony.clone();
+        forged.current.h_query[0] = forged.current.h_query[1];
+        assert!(verify(&forged).is_err());
+
+        let mut forged = ceremony.clone();
+        forged.current.vk.alpha_g1 = forged.current.delta_g1;
+        assert!(verify(&forged).is_err());
+
+        let mut reordered = ceremony;
+        reordered.contributions.swap(0, 1);
+        assert!(verify(&reordered).is_err());
+    }
+}
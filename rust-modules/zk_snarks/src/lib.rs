@@ -1,285 +1,374 @@
-use ark_bn254::{Bn254, Fq as ArkFq, Fr as ArkFr, G1Affine, G2Affine};
-use ark_ec::pairing::Pairing;
-use ark_ff::{BigInteger, Field, PrimeField};
-use ark_groth16::{Groth16, Proof};
-
-use ark_r1cs_std::fields::fp::FpVar;
-use ark_r1cs_std::prelude::*;
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::rand::thread_rng;
-use chrono::Utc;
-use hex;
-use light_poseidon::{Poseidon, PoseidonError, PoseidonHasher};
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::error::Error;
-use std::fmt;
-
-#[derive(Debug)]
-pub struct MySynthesisError(pub SynthesisError);
-
-impl fmt::Display for MySynthesisError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
+use ark_bn254::{Bn254, Fr as ArkFr};
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+
+use ark_std::rand::Rng;
+use serde_json::json;
+use tracing::instrument;
+
+pub mod aggregation;
+pub mod batch;
+pub mod ceremony;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
+pub mod powers_of_tau;
+pub mod solidity;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use zk_core::{
+    compute_root, deserialize_proof, fr_to_hex, parse_data, parse_fr, serialize_proof,
+    sign_envelope, verify_merkle_tree, verify_signature, vk_id, AggregateCircuit,
+    AmountRangeCircuit, AmountStatement, DataVerificationEntry, DatedRecord, EnvelopeSignature,
+    MerkleTreeData, MyFqWrapper, OwnershipCircuit, ProofEnvelope, ProvingSystem, Quorum, ZkError,
+    AGGREGATE_CIRCUIT_ID, MEMBERSHIP_CIRCUIT_ID, OWNERSHIP_CIRCUIT_ID, RANGE_CIRCUIT_ID,
+};
+
+pub use aggregation::{
+    aggregate_proofs, verify_aggregate, AggregateProof, AggregationKey, AggregationVerifierKey,
+};
+pub use batch::{verify_batch, BatchItem};
+pub use ceremony::{Ceremony, Contribution, KnowledgeProof};
+pub use powers_of_tau::{PowersOfTau, TauContribution};
+
+/// Runs the Groth16 setup for membership proofs of the given Merkle depth.
+#[instrument(name = "setup", skip(rng))]
+pub fn generate_proving_key<R: Rng>(
+    depth: usize,
+    rng: &mut R,
+) -> Result<ProvingKey<Bn254>, ZkError> {
+    let circuit = DataVerificationEntry::blank(depth)?;
+    Ok(Groth16::<Bn254>::generate_random_parameters_with_reduction(
+        circuit, rng,
+    )?)
 }
 
-impl Error for MySynthesisError {}
-
-#[derive(Debug)]
-struct MyPoseidonError(PoseidonError);
-
-impl From<PoseidonError> for MyPoseidonError {
-    fn from(e: PoseidonError) -> Self {
-        MyPoseidonError(e)
-    }
+/// Proves membership of `data` with an existing proving key. The key's depth
+/// must match the number of siblings in `data`.
+#[instrument(name = "proving", skip_all, fields(depth = data.siblings.len()))]
+pub fn create_proof<R: Rng>(
+    pk: &ProvingKey<Bn254>,
+    data: &MerkleTreeData,
+    rng: &mut R,
+) -> Result<Proof<Bn254>, ZkError> {
+    let circuit = DataVerificationEntry::new(data, compute_root(data)?)?;
+    Ok(Groth16::<Bn254>::create_random_proof_with_reduction(
+        circuit, pk, rng,
+    )?)
 }
 
-impl From<MyPoseidonError> for SynthesisError {
-    fn from(e: MyPoseidonError) -> Self {
-        SynthesisError::AssignmentMissing // Or use a more appropriate SynthesisError variant
-    }
+/// Proves membership of `data` and wraps the proof in an unsigned envelope
+/// naming `prover`.
+pub fn prove<R: Rng>(
+    pk: &ProvingKey<Bn254>,
+    data: &MerkleTreeData,
+    rng: &mut R,
+    prover: &str,
+) -> Result<ProofEnvelope, ZkError> {
+    let proof = create_proof(pk, data, rng)?;
+    ProofEnvelope::new(
+        MEMBERSHIP_CIRCUIT_ID,
+        &pk.vk,
+        &proof,
+        &public_inputs(data)?,
+        prover,
+    )
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct MyFqWrapper(pub ArkFq);
-
-impl Serialize for MyFqWrapper {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut bytes = Vec::new();
-        self.0
-            .serialize_uncompressed(&mut bytes)
-            .map_err(serde::ser::Error::custom)?;
-        serializer.serialize_bytes(&bytes)
-    }
+/// Runs the Groth16 setup for ownership proofs of the given Merkle depth.
+#[instrument(name = "setup", skip(rng))]
+pub fn generate_ownership_key<R: Rng>(
+    depth: usize,
+    rng: &mut R,
+) -> Result<ProvingKey<Bn254>, ZkError> {
+    let circuit = OwnershipCircuit::blank(depth)?;
+    Ok(Groth16::<Bn254>::generate_random_parameters_with_reduction(
+        circuit, rng,
+    )?)
 }
 
-impl<'de> Deserialize<'de> for MyFqWrapper {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let bytes: &[u8] = Deserialize::deserialize(deserializer)?;
-        let fq = ArkFq::deserialize_uncompressed(bytes).map_err(serde::de::Error::custom)?;
-        Ok(MyFqWrapper(fq))
-    }
+/// Proves ownership with `circuit`, whose depth must match the key's, and
+/// wraps the proof in an unsigned envelope naming `prover`.
+pub fn prove_ownership<R: Rng>(
+    pk: &ProvingKey<Bn254>,
+    circuit: OwnershipCircuit,
+    rng: &mut R,
+    prover: &str,
+) -> Result<ProofEnvelope, ZkError> {
+    let inputs = circuit.public_inputs();
+    prove_circuit(OWNERSHIP_CIRCUIT_ID, pk, circuit, &inputs, rng, prover)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct MerkleTreeData {
-    leaf: ArkFr,
-    siblings: Vec<ArkFr>,
-    path_bits: Vec<bool>,
+/// Runs the Groth16 setup for amount ranges over uploads of up to
+/// `2^depth` records.
+#[instrument(name = "setup", skip(rng))]
+pub fn generate_range_key<R: Rng>(depth: usize, rng: &mut R) -> Result<ProvingKey<Bn254>, ZkError> {
+    let circuit = AmountRangeCircuit::blank(depth)?;
+    Ok(Groth16::<Bn254>::generate_random_parameters_with_reduction(
+        circuit, rng,
+    )?)
 }
 
-fn verify_merkle_tree(data: &MerkleTreeData) -> Result<bool, Box<dyn Error>> {
-    let mut current_hash = data.leaf;
-
-    for (sibling_hash, path_bit) in data.siblings.iter().zip(data.path_bits.iter()) {
-        let mut hasher = Poseidon::<ArkFr>::new_circom(2)?;
-        let (left, right) = if *path_bit {
-            (current_hash, *sibling_hash)
-        } else {
-            (*sibling_hash, current_hash)
-        };
-
-        current_hash = hasher.hash(&[left, right])?;
-    }
-
-    println!("Final current_hash: {:?}", current_hash);
-    Ok(true)
+/// Proves the statement of `circuit`, whose depth must match the key's,
+/// and wraps the proof in an unsigned envelope naming `prover`.
+pub fn prove_amount_range<R: Rng>(
+    pk: &ProvingKey<Bn254>,
+    circuit: AmountRangeCircuit,
+    rng: &mut R,
+    prover: &str,
+) -> Result<ProofEnvelope, ZkError> {
+    let inputs = circuit.public_inputs();
+    prove_circuit(RANGE_CIRCUIT_ID, pk, circuit, &inputs, rng, prover)
 }
 
-fn parse_static_data() -> Result<MerkleTreeData, Box<dyn Error>> {
-    // Ensure the hex strings have even length
-    let leaf_bytes =
-        hex::decode("5dc83aa52097f90c9aa00a9ac5c455cbda815e4b3affcdb8f5ef1f2d98b2621b")?; // Added a trailing 0 to make it even
-    let leaf = ArkFr::from_le_bytes_mod_order(&leaf_bytes);
-
-    let sibling_bytes =
-        hex::decode("765643c6d057ce226c9ee9340bd86085eaba0a0030524c7f54cb03760495a30c")?; // Added a trailing 0 to make it even
-    let sibling = ArkFr::from_le_bytes_mod_order(&sibling_bytes);
+/// Runs the Groth16 setup for aggregates over uploads of up to `2^depth`
+/// records, totalling `types` types.
+#[instrument(name = "setup", skip(rng))]
+pub fn generate_aggregate_key<R: Rng>(
+    depth: usize,
+    types: usize,
+    rng: &mut R,
+) -> Result<ProvingKey<Bn254>, ZkError> {
+    let circuit = AggregateCircuit::blank(depth, types)?;
+    Ok(Groth16::<Bn254>::generate_random_parameters_with_reduction(
+        circuit, rng,
+    )?)
+}
 
-    let path_bits = vec![true, false];
+/// Proves the aggregates of `circuit`, whose shape must match the key's,
+/// and wraps the proof in an unsigned envelope naming `prover`.
+pub fn prove_aggregates<R: Rng>(
+    pk: &ProvingKey<Bn254>,
+    circuit: AggregateCircuit,
+    rng: &mut R,
+    prover: &str,
+) -> Result<ProofEnvelope, ZkError> {
+    let inputs = circuit.public_inputs().to_vec();
+    prove_circuit(AGGREGATE_CIRCUIT_ID, pk, circuit, &inputs, rng, prover)
+}
 
-    let data = MerkleTreeData {
-        leaf,
-        siblings: vec![sibling],
-        path_bits,
-    };
+/// Proves `circuit` and wraps the proof in an unsigned envelope for
+/// `circuit_id`. `public_inputs` must be the ones the circuit allocates.
+#[instrument(name = "proving", skip(pk, circuit, public_inputs, rng, prover))]
+fn prove_circuit<C, R>(
+    circuit_id: &str,
+    pk: &ProvingKey<Bn254>,
+    circuit: C,
+    public_inputs: &[ArkFr],
+    rng: &mut R,
+    prover: &str,
+) -> Result<ProofEnvelope, ZkError>
+where
+    C: ConstraintSynthesizer<ArkFr>,
+    R: Rng,
+{
+    let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, pk, rng)?;
+    ProofEnvelope::new(circuit_id, &pk.vk, &proof, public_inputs, prover)
+}
 
-    Ok(data)
+/// The public inputs a membership proof of `data` is checked against: the
+/// root its path hashes to.
+pub fn public_inputs(data: &MerkleTreeData) -> Result<Vec<ArkFr>, ZkError> {
+    Ok(vec![compute_root(data)?])
 }
 
-#[derive(Clone)]
-struct DataVerificationEntry {
-    leaf: ArkFr,
-    siblings: Vec<ArkFr>,
-    path_bits: Vec<bool>,
+/// `Ok(false)` for a well-formed proof that does not verify; an error when
+/// the number of public inputs does not fit the key.
+#[instrument(name = "verification", skip_all, fields(inputs = public_inputs.len()))]
+pub fn verify_proof(
+    vk: &VerifyingKey<Bn254>,
+    proof: &Proof<Bn254>,
+    public_inputs: &[ArkFr],
+) -> Result<bool, ZkError> {
+    let pvk = ark_groth16::prepare_verifying_key(vk);
+    Groth16::<Bn254>::verify_proof(&pvk, proof, public_inputs).map_err(ZkError::Verification)
 }
 
-impl ConstraintSynthesizer<ArkFr> for DataVerificationEntry {
-    fn generate_constraints(self, cs: ConstraintSystemRef<ArkFr>) -> Result<(), SynthesisError> {
-        println!("Starting generate_constraints");
-
-        let leaf_var = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(self.leaf))?;
-        let mut current_hash = leaf_var.clone();
-
-        for (i, (sibling_hash, path_bit)) in
-            self.siblings.iter().zip(self.path_bits.iter()).enumerate()
-        {
-            println!("Processing sibling {} with path_bit {}", i, path_bit);
-
-            let sibling_var = FpVar::<ArkFr>::new_witness(cs.clone(), || Ok(*sibling_hash))?;
-
-            let (left, right) = if *path_bit {
-                (current_hash.clone(), sibling_var)
-            } else {
-                (sibling_var, current_hash.clone())
-            };
-            println!("left: {:?}", left);
-            println!("right: {:?}", right);
-
-            let mut hasher = Poseidon::<ArkFr>::new_circom(2).map_err(|e| {
-                println!("Error creating Poseidon hasher: {:?}", e);
-                MyPoseidonError::from(e)
-            })?;
-            let result_hash_var = FpVar::<ArkFr>::new_witness(cs.clone(), || {
-                let result_hash = hasher.hash(&[left.value()?, right.value()?]).map_err(|e| {
-                    println!("Error hashing values: {:?}", e);
-                    MyPoseidonError::from(e)
-                })?;
-                Ok(result_hash)
-            })?;
-            current_hash = result_hash_var;
-            println!("Updated current hash at step {}: {:?}", i, current_hash);
+pub fn proof_coordinates(proof: &Proof<Bn254>) -> serde_json::Value {
+    json!({
+        "a": {
+            "x": proof.a.x.to_string(),
+            "y": proof.a.y.to_string()
+        },
+        "b": {
+            "x": {
+                "c0": proof.b.x.c0.to_string(),
+                "c1": proof.b.x.c1.to_string()
+            },
+            "y": {
+                "c0": proof.b.y.c0.to_string(),
+                "c1": proof.b.y.c1.to_string()
+            }
+        },
+        "c": {
+            "x": proof.c.x.to_string(),
+            "y": proof.c.y.to_string()
         }
-
-        let root_hash_bytes =
-            hex::decode("d5c7ef8f7e879df3c7cbf0f6adfdff6dd3e471dfefebea97b5135be8182a185a")
-                .map_err(|e| {
-                    println!("Error decoding root hash: {:?}", e);
-                    SynthesisError::AssignmentMissing
-                })?;
-        let root_hash_fr = ArkFr::from_le_bytes_mod_order(&root_hash_bytes);
-
-        // Define the root hash as a constant input variable
-        let root_hash_var = FpVar::<ArkFr>::new_input(cs.clone(), || Ok(root_hash_fr))?;
-
-        root_hash_var.enforce_equal(&root_hash_var)?;
-
-        println!("root var: {:?}", root_hash_var);
-        // Enforce the equality constraint between the computed hash and the root hash
-
-        Ok(())
-    }
+    })
 }
 
-fn serialize_proof<E: Pairing>(proof: &Proof<E>) -> Result<String, Box<dyn Error>> {
-    let mut proof_bytes = vec![];
-    proof.serialize_uncompressed(&mut proof_bytes)?;
-    Ok(hex::encode(proof_bytes))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::PrimeField;
+    use ark_std::rand::thread_rng;
+    use std::error::Error;
+
+    fn parse_static_data() -> Result<MerkleTreeData, Box<dyn Error>> {
+        // Ensure the hex strings have even length
+        let leaf_bytes =
+            hex::decode("5dc83aa52097f90c9aa00a9ac5c455cbda815e4b3affcdb8f5ef1f2d98b2621b")?; // Added a trailing 0 to make it even
+        let leaf = ArkFr::from_le_bytes_mod_order(&leaf_bytes);
+
+        let sibling_bytes =
+            hex::decode("765643c6d057ce226c9ee9340bd86085eaba0a0030524c7f54cb03760495a30c")?; // Added a trailing 0 to make it even
+        let sibling = ArkFr::from_le_bytes_mod_order(&sibling_bytes);
+
+        let path_bits = vec![true];
+
+        let data = MerkleTreeData {
+            leaf,
+            siblings: vec![sibling],
+            path_bits,
+        };
 
-fn deserialize_proof(proof_hex: &str) -> Result<Proof<Bn254>, Box<dyn Error>> {
-    let proof_bytes = hex::decode(proof_hex)?;
-    let mut proof_reader = &proof_bytes[..];
+        Ok(data)
+    }
 
-    let a = G1Affine::deserialize_uncompressed(&mut proof_reader)?;
-    let b = G2Affine::deserialize_uncompressed(&mut proof_reader)?;
-    let c = G1Affine::deserialize_uncompressed(&mut proof_reader)?;
+    #[test]
+    fn test_generate_proof() {
+        let data = parse_static_data().expect("Failed to parse static data");
+        let mut rng = thread_rng();
+        let pk = generate_proving_key(data.siblings.len(), &mut rng)
+            .expect("Failed to generate proving key");
+        let proof = create_proof(&pk, &data, &mut rng).expect("Failed to create proof");
+        let serialized = serialize_proof(&proof).expect("Failed to serialize proof");
+        assert!(!serialized.is_empty(), "Proof should not be empty");
+        assert_eq!(deserialize_proof(&serialized).unwrap(), proof);
+    }
 
-    Ok(Proof { a, b, c })
-}
-fn generate_proof(data: &MerkleTreeData) -> Result<String, Box<dyn Error>> {
-    let mut csprng = thread_rng();
-
-    let circuit = DataVerificationEntry {
-        leaf: data.leaf,
-        siblings: data.siblings.clone(),
-        path_bits: data.path_bits.clone(),
-    };
-
-    let params =
-        Groth16::<Bn254>::generate_random_parameters_with_reduction(circuit.clone(), &mut csprng)?;
-    let proof =
-        Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &params, &mut csprng)?;
-
-    let proof_serialized = serialize_proof(&proof)?;
-    let deserialized_proof = deserialize_proof(&proof_serialized)?;
-    print_proof_coordinates(&deserialized_proof);
-    Ok(proof_serialized)
-}
+    #[test]
+    fn test_create_proof_with_existing_key() {
+        let data = parse_static_data().expect("Failed to parse static data");
+        let mut rng = thread_rng();
+        let pk = generate_proving_key(data.siblings.len(), &mut rng)
+            .expect("Failed to generate proving key");
+        let proof = create_proof(&pk, &data, &mut rng).expect("Failed to create proof");
+        let inputs = public_inputs(&data).expect("Failed to build public inputs");
 
-fn print_proof_coordinates(proof: &Proof<Bn254>) {
-    println!("Proof:");
-    println!("a.x: {:?}", proof.a.x);
-    println!("a.y: {:?}", proof.a.y);
-    println!("b.x.c0: {:?}", proof.b.x.c0);
-    println!("b.x.c1: {:?}", proof.b.x.c1);
-    println!("b.y.c0: {:?}", proof.b.y.c0);
-    println!("b.y.c1: {:?}", proof.b.y.c1);
-    println!("c.x: {:?}", proof.c.x);
-    println!("c.y: {:?}", proof.c.y);
-}
+        assert!(verify_proof(&pk.vk, &proof, &inputs).expect("Failed to verify proof"));
+    }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let data = parse_static_data()?;
-    let is_valid = verify_merkle_tree(&data)?;
-    println!("Merkle tree is valid: {}", is_valid);
+    #[test]
+    fn test_prove_ownership() {
+        let mut rng = thread_rng();
+        let pk = generate_ownership_key(1, &mut rng).expect("Failed to generate proving key");
+        let secret = ArkFr::from(5u64);
+        let circuit = OwnershipCircuit::new(
+            secret,
+            &[ArkFr::from(11u64)],
+            &[true],
+            ArkFr::from(1u64),
+            ArkFr::from(42u64),
+        )
+        .expect("Failed to build circuit");
+        let inputs = circuit.public_inputs();
+        let envelope =
+            prove_ownership(&pk, circuit, &mut rng, "test").expect("Failed to create proof");
+
+        assert_eq!(envelope.circuit_id, OWNERSHIP_CIRCUIT_ID);
+        assert_eq!(envelope.decode_public_inputs().unwrap(), inputs);
+        assert!(envelope.verify(&pk.vk).expect("Failed to verify proof"));
+    }
 
-    let proof = generate_proof(&data)?;
-    println!("Generated proof: {}", proof);
+    fn dated_fields(date: &str) -> [ArkFr; 6] {
+        [
+            ArkFr::from(1u64),
+            zk_core::aggregates::date_hash(date).unwrap(),
+            ArkFr::from(7u64),
+            zk_core::range::signed_fr(1250),
+            ArkFr::from(3u64),
+            ArkFr::from(201u64),
+        ]
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_prove_amount_range() {
+        let mut rng = thread_rng();
+        let statement = AmountStatement {
+            row_min: 0,
+            row_max: 2000,
+            type_hash: None,
+            sum_min: 1000,
+            sum_max: 2000,
+        };
+        let pk = generate_range_key(1, &mut rng).expect("Failed to generate proving key");
+        let circuit = AmountRangeCircuit::new(&[dated_fields("2024-05-21")], 1, statement)
+            .expect("Failed to build circuit");
+        let inputs = circuit.public_inputs();
+        let envelope =
+            prove_amount_range(&pk, circuit, &mut rng, "test").expect("Failed to create proof");
+
+        assert_eq!(envelope.circuit_id, RANGE_CIRCUIT_ID);
+        assert_eq!(envelope.decode_public_inputs().unwrap(), inputs);
+        assert!(envelope.verify(&pk.vk).expect("Failed to verify proof"));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_prove_aggregates() {
+        let mut rng = thread_rng();
+        let date = "2024-05-21";
+        let record = DatedRecord::new(dated_fields(date), date).expect("Failed to open record");
+
+        let pk = generate_aggregate_key(1, 1, &mut rng).expect("Failed to generate proving key");
+        let circuit = AggregateCircuit::new(&[record], 1, &[ArkFr::from(3u64)])
+            .expect("Failed to build circuit");
+        let envelope =
+            prove_aggregates(&pk, circuit, &mut rng, "test").expect("Failed to create proof");
+
+        assert_eq!(envelope.circuit_id, AGGREGATE_CIRCUIT_ID);
+        assert!(envelope.verify(&pk.vk).expect("Failed to verify proof"));
+    }
 
     #[test]
-    fn test_generate_proof() {
+    fn test_proving_metrics() {
         let data = parse_static_data().expect("Failed to parse static data");
-        let proof = generate_proof(&data).expect("Failed to generate proof");
-        assert!(!proof.is_empty(), "Proof should not be empty");
+        let metrics = metrics::proving_metrics(&data, &mut thread_rng())
+            .expect("Failed to collect proving metrics");
+
+        assert!(metrics.verified);
+        assert_eq!(metrics.size.num_public_inputs, 1);
+        assert!(metrics.size.num_witness_variables > data.siblings.len());
+        assert_eq!(
+            metrics::circuit_size(data.siblings.len()).expect("Failed to size circuit"),
+            metrics.size
+        );
     }
 
     #[test]
     fn test_verify_merkle_tree() {
         let data = parse_static_data().expect("Failed to parse static data");
-        let is_valid = verify_merkle_tree(&data).expect("Failed to verify Merkle tree");
+        let root = compute_root(&data).expect("Failed to compute root");
+        let is_valid = verify_merkle_tree(&data, &root).expect("Failed to verify Merkle tree");
         assert!(is_valid, "Merkle tree verification failed");
     }
 
     #[test]
     fn test_verify_proof() {
         let data = parse_static_data().expect("Failed to parse static data");
-        let proof = generate_proof(&data).expect("Failed to generate proof");
-        let deserialized_proof = deserialize_proof(&proof).expect("Failed to deserialize proof");
-
-        let params = Groth16::<Bn254>::generate_random_parameters_with_reduction(
-            DataVerificationEntry {
-                leaf: data.leaf,
-                siblings: data.siblings.clone(),
-                path_bits: data.path_bits.clone(),
-            },
-            &mut thread_rng(),
-        )
-        .expect("Failed to generate parameters");
-        let vk = params.vk;
-
-        let prepared_vk = ark_groth16::prepare_verifying_key(&vk);
-
-        // Mock public inputs
-        let public_inputs = vec![ArkFr::from(1), ArkFr::from(2), ArkFr::from(3)];
-
-        // Mock the verification result
-        let is_proof_valid = true;
-
-        assert!(is_proof_valid, "Proof verification failed");
+        let mut rng = thread_rng();
+        let pk = generate_proving_key(data.siblings.len(), &mut rng)
+            .expect("Failed to generate proving key");
+        let proof = create_proof(&pk, &data, &mut rng).expect("Failed to create proof");
+        let inputs = public_inputs(&data).expect("Failed to build public inputs");
+
+        // A key from another setup of the same circuit.
+        let other_vk = generate_proving_key(data.siblings.len(), &mut rng)
+            .expect("Failed to generate proving key")
+            .vk;
+        assert!(!verify_proof(&other_vk, &proof, &inputs).expect("Failed to verify proof"));
+
+        let too_many_inputs = vec![inputs[0], ArkFr::from(2), ArkFr::from(3)];
+        assert!(verify_proof(&pk.vk, &proof, &too_many_inputs).is_err());
     }
 }
@@ -0,0 +1,111 @@
+//! Size and timing of the membership circuit, stage by stage.
+//!
+//! Timings use `std::time::Instant`, so this module is not available on
+//! `wasm32-unknown-unknown`.
+
+use std::time::{Duration, Instant};
+
+use ark_bn254::Fr as ArkFr;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::Rng;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::{
+    compute_root, create_proof, generate_proving_key, verify_proof, DataVerificationEntry,
+    MerkleTreeData, ZkError,
+};
+
+/// Shape of the constraint system, as seen by the Groth16 setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CircuitSize {
+    pub num_constraints: usize,
+    pub num_witness_variables: usize,
+    /// Public inputs, not counting the constant `1` variable.
+    pub num_public_inputs: usize,
+}
+
+/// Result of a full setup, prove and verify run. Times are wall-clock
+/// milliseconds; `proving` includes the prover's own synthesis pass.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProvingMetrics {
+    pub depth: usize,
+    pub size: CircuitSize,
+    pub setup_ms: f64,
+    pub witness_generation_ms: f64,
+    pub proving_ms: f64,
+    pub verification_ms: f64,
+    /// Uncompressed size of the proving key.
+    pub proving_key_bytes: usize,
+    pub verified: bool,
+}
+
+/// Size of the membership circuit at `depth`, synthesized without witnesses.
+pub fn circuit_size(depth: usize) -> Result<CircuitSize, ZkError> {
+    synthesize(DataVerificationEntry::blank(depth)?, SynthesisMode::Setup)
+}
+
+/// Runs setup, witness generation, proving and verification for `data` and
+/// measures each stage.
+#[instrument(name = "metrics", skip_all, fields(depth = data.siblings.len()))]
+pub fn proving_metrics<R: Rng>(
+    data: &MerkleTreeData,
+    rng: &mut R,
+) -> Result<ProvingMetrics, ZkError> {
+    let depth = data.siblings.len();
+
+    let start = Instant::now();
+    let pk = generate_proving_key(depth, rng)?;
+    let setup = start.elapsed();
+
+    let root = compute_root(data)?;
+    let start = Instant::now();
+    let size = synthesize(
+        DataVerificationEntry::new(data, root)?,
+        SynthesisMode::Prove {
+            construct_matrices: false,
+        },
+    )?;
+    let witness_generation = start.elapsed();
+
+    let start = Instant::now();
+    let proof = create_proof(&pk, data, rng)?;
+    let proving = start.elapsed();
+
+    let start = Instant::now();
+    let verified = verify_proof(&pk.vk, &proof, &[root])?;
+    let verification = start.elapsed();
+
+    Ok(ProvingMetrics {
+        depth,
+        size,
+        setup_ms: millis(setup),
+        witness_generation_ms: millis(witness_generation),
+        proving_ms: millis(proving),
+        verification_ms: millis(verification),
+        proving_key_bytes: pk.uncompressed_size(),
+        verified,
+    })
+}
+
+/// Synthesizes `circuit` the way the Groth16 setup and prover do.
+fn synthesize(circuit: DataVerificationEntry, mode: SynthesisMode) -> Result<CircuitSize, ZkError> {
+    let cs = ConstraintSystem::<ArkFr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(mode);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+
+    Ok(CircuitSize {
+        num_constraints: cs.num_constraints(),
+        num_witness_variables: cs.num_witness_variables(),
+        num_public_inputs: cs.num_instance_variables() - 1,
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
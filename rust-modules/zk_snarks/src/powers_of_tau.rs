@@ -0,0 +1,361 @@
+//! Phase-1 ("powers of tau") setup, shared by every circuit up to a size.
+//!
+//! The transcript holds `τ^i` in G1 and G2 and `α τ^i`, `β τ^i` in G1. Each
+//! participant multiplies `τ`, `α` and `β` by secret factors of their own
+//! and publishes proofs of knowledge of them, so the three stay unknown
+//! unless every participant kept their factors. `Ceremony::new` derives a
+//! circuit's initial Groth16 key from the transcript alone.
+
+use std::iter::successors;
+
+use ark_bn254::{Fr as ArkFr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{One, UniformRand};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument};
+
+use crate::ceremony::{same_ratio, KnowledgeProof};
+use crate::ZkError;
+
+/// One participant's public record: `τ`, `α` and `β` in G1 after their
+/// update, and proofs that they know the factors of each.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TauContribution {
+    pub tau_after: G1Affine,
+    pub alpha_after: G1Affine,
+    pub beta_after: G1Affine,
+    pub tau_proof: KnowledgeProof,
+    pub alpha_proof: KnowledgeProof,
+    pub beta_proof: KnowledgeProof,
+}
+
+/// Powers of `τ` for QAP domains of up to `size` points, and every
+/// contribution that produced them.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PowersOfTau {
+    /// `τ^i` in G1 for `i < 2 * size - 1`, enough for the `H` query.
+    tau_g1: Vec<G1Affine>,
+    /// `τ^i` in G2 for `i < size`.
+    tau_g2: Vec<G2Affine>,
+    alpha_tau_g1: Vec<G1Affine>,
+    beta_tau_g1: Vec<G1Affine>,
+    beta_g2: G2Affine,
+    contributions: Vec<TauContribution>,
+}
+
+/// The transcript in the Lagrange basis of a circuit's QAP domain.
+pub(crate) struct LagrangeBasis {
+    pub tau_g1: Vec<G1Projective>,
+    pub tau_g2: Vec<G2Projective>,
+    pub alpha_tau_g1: Vec<G1Projective>,
+    pub beta_tau_g1: Vec<G1Projective>,
+}
+
+fn invalid(reason: String) -> ZkError {
+    ZkError::InvalidInput {
+        field: "powers_of_tau",
+        reason,
+    }
+}
+
+impl PowersOfTau {
+    /// An empty transcript, with `τ = α = β = 1`, for circuits whose number
+    /// of constraints and public inputs together is at most `size`, a power
+    /// of two.
+    pub fn new(size: usize) -> Result<Self, ZkError> {
+        if size < 2 || !size.is_power_of_two() {
+            return Err(invalid(format!("size {} is not a power of two", size)));
+        }
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        Ok(PowersOfTau {
+            tau_g1: vec![g1; 2 * size - 1],
+            tau_g2: vec![g2; size],
+            alpha_tau_g1: vec![g1; size],
+            beta_tau_g1: vec![g1; size],
+            beta_g2: g2,
+            contributions: Vec::new(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    pub fn contributions(&self) -> &[TauContribution] {
+        &self.contributions
+    }
+
+    /// Hash of the transcript after `count` contributions; with `count = 0`,
+    /// of its size.
+    fn hash_after(&self, count: usize) -> Result<[u8; 32], ZkError> {
+        let mut bytes = b"powers_of_tau".to_vec();
+        (self.size() as u64).serialize_uncompressed(&mut bytes)?;
+        let mut hash: [u8; 32] = Sha256::digest(&bytes).into();
+
+        for contribution in &self.contributions[..count] {
+            let mut bytes = hash.to_vec();
+            contribution.serialize_uncompressed(&mut bytes)?;
+            hash = Sha256::digest(&bytes).into();
+        }
+        Ok(hash)
+    }
+
+    /// Multiplies `τ`, `α` and `β` by fresh secrets, which are dropped on
+    /// return, and returns the contribution hash for the participant to
+    /// publish.
+    #[instrument(name = "contribution", skip_all, fields(index = self.contributions.len()))]
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> Result<[u8; 32], ZkError> {
+        let transcript = self.hash_after(self.contributions.len())?;
+        let (tau, alpha, beta) = (ArkFr::rand(rng), ArkFr::rand(rng), ArkFr::rand(rng));
+        let powers: Vec<ArkFr> = successors(Some(ArkFr::one()), |power| Some(*power * tau))
+            .take(self.tau_g1.len())
+            .collect();
+
+        self.tau_g1 = scale::<G1Projective>(&self.tau_g1, powers.iter().copied());
+        self.tau_g2 = scale::<G2Projective>(&self.tau_g2, powers.iter().copied());
+        self.alpha_tau_g1 =
+            scale::<G1Projective>(&self.alpha_tau_g1, powers.iter().map(|p| alpha * p));
+        self.beta_tau_g1 =
+            scale::<G1Projective>(&self.beta_tau_g1, powers.iter().map(|p| beta * p));
+        self.beta_g2 = (self.beta_g2 * beta).into_affine();
+
+        self.contributions.push(TauContribution {
+            tau_after: self.tau_g1[1],
+            alpha_after: self.alpha_tau_g1[0],
+            beta_after: self.beta_tau_g1[0],
+            tau_proof: KnowledgeProof::new(&transcript, tau, rng)?,
+            alpha_proof: KnowledgeProof::new(&transcript, alpha, rng)?,
+            beta_proof: KnowledgeProof::new(&transcript, beta, rng)?,
+        });
+        let hash = self.hash_after(self.contributions.len())?;
+        info!(hash = %hex::encode(hash), "contributed");
+        Ok(hash)
+    }
+
+    /// Checks every contribution and that the transcript holds the powers of
+    /// the `τ`, `α` and `β` they produced. Returns the contribution hashes in
+    /// order, so each participant can find the one they published.
+    #[instrument(name = "verification", skip_all, fields(contributions = self.contributions.len()))]
+    pub fn verify<R: Rng>(&self, rng: &mut R) -> Result<Vec<[u8; 32]>, ZkError> {
+        if self.contributions.is_empty() {
+            return Err(invalid("no contributions".to_string()));
+        }
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+
+        let mut hashes = Vec::new();
+        let (mut tau, mut alpha, mut beta) = (g1, g1, g1);
+        for (i, contribution) in self.contributions.iter().enumerate() {
+            let transcript = self.hash_after(i)?;
+            for (name, before, after, proof) in [
+                ("τ", tau, contribution.tau_after, &contribution.tau_proof),
+                (
+                    "α",
+                    alpha,
+                    contribution.alpha_after,
+                    &contribution.alpha_proof,
+                ),
+                ("β", beta, contribution.beta_after, &contribution.beta_proof),
+            ] {
+                let updated = proof
+                    .ratio(&transcript)?
+                    .is_some_and(|ratio| same_ratio((before, after), ratio));
+                if !updated {
+                    return Err(invalid(format!(
+                        "contribution {} does not update {}",
+                        i, name
+                    )));
+                }
+            }
+            (tau, alpha, beta) = (
+                contribution.tau_after,
+                contribution.alpha_after,
+                contribution.beta_after,
+            );
+            hashes.push(self.hash_after(i + 1)?);
+        }
+
+        let size = self.size();
+        let well_formed = size >= 2
+            && size.is_power_of_two()
+            && self.tau_g1.len() == 2 * size - 1
+            && self.alpha_tau_g1.len() == size
+            && self.beta_tau_g1.len() == size
+            && self.tau_g1[0] == g1
+            && self.tau_g2[0] == g2
+            && self.tau_g1[1] == tau
+            && self.alpha_tau_g1[0] == alpha
+            && self.beta_tau_g1[0] == beta;
+        if !well_formed {
+            return Err(invalid(
+                "the powers do not start from the last contribution".to_string(),
+            ));
+        }
+
+        // Every sequence must advance by the same `τ`, checked on a random
+        // combination of its consecutive pairs.
+        let tau_g2 = (g2, self.tau_g2[1]);
+        let consistent = same_ratio((g1, tau), tau_g2)
+            && same_ratio((g1, beta), (g2, self.beta_g2))
+            && same_ratio(consecutive::<G1Projective, _>(&self.tau_g1, rng), tau_g2)
+            && same_ratio(
+                consecutive::<G1Projective, _>(&self.alpha_tau_g1, rng),
+                tau_g2,
+            )
+            && same_ratio(
+                consecutive::<G1Projective, _>(&self.beta_tau_g1, rng),
+                tau_g2,
+            )
+            && same_ratio((g1, tau), consecutive::<G2Projective, _>(&self.tau_g2, rng));
+        if !consistent {
+            return Err(invalid("the powers are not powers of one τ".to_string()));
+        }
+
+        debug!("powers of tau verified");
+        Ok(hashes)
+    }
+
+    pub(crate) fn alpha_g1(&self) -> G1Affine {
+        self.alpha_tau_g1[0]
+    }
+
+    pub(crate) fn beta_g1(&self) -> G1Affine {
+        self.beta_tau_g1[0]
+    }
+
+    pub(crate) fn beta_g2(&self) -> G2Affine {
+        self.beta_g2
+    }
+
+    /// Interpolates the first `domain.size()` powers into `L_j(τ)`, the
+    /// Lagrange polynomials of `domain` at `τ`.
+    pub(crate) fn lagrange_basis(
+        &self,
+        domain: &GeneralEvaluationDomain<ArkFr>,
+    ) -> Result<LagrangeBasis, ZkError> {
+        let size = domain.size();
+        if size > self.size() {
+            return Err(invalid(format!(
+                "a domain of {} points needs a larger transcript than {}",
+                size,
+                self.size()
+            )));
+        }
+        Ok(LagrangeBasis {
+            tau_g1: interpolate::<G1Projective>(domain, &self.tau_g1[..size]),
+            tau_g2: interpolate::<G2Projective>(domain, &self.tau_g2[..size]),
+            alpha_tau_g1: interpolate::<G1Projective>(domain, &self.alpha_tau_g1[..size]),
+            beta_tau_g1: interpolate::<G1Projective>(domain, &self.beta_tau_g1[..size]),
+        })
+    }
+
+    /// `τ^i Z(τ)` for `i < size - 1`, where `Z(x) = x^size - 1` vanishes on
+    /// the domain: Groth16's `H` query before division by `δ`.
+    pub(crate) fn h_query(&self, size: usize) -> Vec<G1Affine> {
+        let h: Vec<G1Projective> = (0..size - 1)
+            .map(|i| self.tau_g1[i + size].into_group() - self.tau_g1[i])
+            .collect();
+        G1Projective::normalize_batch(&h)
+    }
+}
+
+fn scale<G: CurveGroup<ScalarField = ArkFr>>(
+    points: &[G::Affine],
+    factors: impl Iterator<Item = ArkFr>,
+) -> Vec<G::Affine> {
+    let scaled: Vec<G> = points
+        .iter()
+        .zip(factors)
+        .map(|(point, factor)| *point * factor)
+        .collect();
+    G::normalize_batch(&scaled)
+}
+
+/// Random combinations `Σ w_i p_i` and `Σ w_i p_{i+1}`, which have the same
+/// ratio as every consecutive pair if all pairs do, and otherwise a different
+/// one with overwhelming probability.
+fn consecutive<G: CurveGroup<ScalarField = ArkFr>, R: Rng>(
+    points: &[G::Affine],
+    rng: &mut R,
+) -> (G::Affine, G::Affine) {
+    let weights: Vec<ArkFr> = (1..points.len()).map(|_| ArkFr::rand(rng)).collect();
+    let n = points.len() - 1;
+    (
+        G::msm_unchecked(&points[..n], &weights).into_affine(),
+        G::msm_unchecked(&points[1..], &weights).into_affine(),
+    )
+}
+
+fn interpolate<G: CurveGroup<ScalarField = ArkFr>>(
+    domain: &GeneralEvaluationDomain<ArkFr>,
+    powers: &[G::Affine],
+) -> Vec<G> {
+    let mut points: Vec<G> = powers.iter().map(|point| point.into_group()).collect();
+    domain.ifft_in_place(&mut points);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::rand::SeedableRng;
+    use ark_std::test_rng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn transcript(participants: u64) -> PowersOfTau {
+        let mut powers = PowersOfTau::new(8).unwrap();
+        for participant in 0..participants {
+            // Each participant receives the encoded transcript and passes it on.
+            let mut bytes = Vec::new();
+            powers.serialize_compressed(&mut bytes).unwrap();
+            powers = PowersOfTau::deserialize_compressed(&bytes[..]).unwrap();
+            powers
+                .contribute(&mut ChaCha20Rng::seed_from_u64(participant))
+                .unwrap();
+        }
+        powers
+    }
+
+    #[test]
+    fn test_contributions_chain_and_verify() {
+        let powers = transcript(3);
+        assert_eq!(powers.verify(&mut test_rng()).unwrap().len(), 3);
+        assert!(transcript(0).verify(&mut test_rng()).is_err());
+        assert!(PowersOfTau::new(12).is_err());
+
+        // The Lagrange basis sums to the generator: Σ L_j(x) = 1.
+        let domain = GeneralEvaluationDomain::<ArkFr>::new(4).unwrap();
+        let basis = powers.lagrange_basis(&domain).unwrap();
+        let sum: G1Projective = basis.tau_g1.iter().sum();
+        assert_eq!(sum.into_affine(), G1Affine::generator());
+        let domain = GeneralEvaluationDomain::<ArkFr>::new(16).unwrap();
+        assert!(powers.lagrange_basis(&domain).is_err());
+    }
+
+    #[test]
+    fn test_rejects_tampered_transcript() {
+        let powers = transcript(2);
+
+        let mut forged = powers.clone();
+        forged.tau_g1[5] = forged.tau_g1[4];
+        assert!(forged.verify(&mut test_rng()).is_err());
+
+        let mut forged = powers.clone();
+        forged.alpha_tau_g1[3] = (forged.alpha_tau_g1[3] * ArkFr::from(2u64)).into_affine();
+        assert!(forged.verify(&mut test_rng()).is_err());
+
+        // β replaced by one whose factor is known, without a matching proof.
+        let mut forged = powers.clone();
+        let beta = ArkFr::from(3u64);
+        forged.beta_tau_g1 = scale::<G1Projective>(&forged.beta_tau_g1, std::iter::repeat(beta));
+        forged.beta_g2 = (forged.beta_g2 * beta).into_affine();
+        forged.contributions[1].beta_after = forged.beta_tau_g1[0];
+        assert!(forged.verify(&mut test_rng()).is_err());
+
+        let mut truncated = powers;
+        truncated.tau_g1.pop();
+        assert!(truncated.verify(&mut test_rng()).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Solidity verifier contracts and call data for on-chain verification.
+//!
+//! The generated `Verifier` has the `verifyProof(a, b, c, input)` signature
+//! that `ProofStorage` in `packages/hardhat` inherits, and checks the proof
+//! with the BN254 precompiles of EIP-196 and EIP-197. Those take an `Fq2`
+//! element as `(c1, c0)`, so G2 points are written imaginary part first,
+//! here and in the call data.
+
+use ark_bn254::{Bn254, Fq, Fr as ArkFr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+
+use crate::{vk_id, ZkError};
+
+fn g1(point: &G1Affine) -> [Fq; 2] {
+    [point.x, point.y]
+}
+
+fn g2(point: &G2Affine) -> [Fq; 4] {
+    [point.x.c1, point.x.c0, point.y.c1, point.y.c0]
+}
+
+fn quoted_hex<F: PrimeField>(values: &[F]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"0x{}\"", hex::encode(value.into_bigint().to_bytes_be())))
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Source of a `Verifier` contract for `vk`, taking one `input` per public
+/// input of the key.
+pub fn verifier_contract(vk: &VerifyingKey<Bn254>) -> Result<String, ZkError> {
+    // e(A, B) · e(α, -β) · e(vkX, -γ) · e(C, -δ) = 1, with the key's points
+    // as constants between the caller's.
+    let mut pairing = vec!["a[0]".to_string(), "a[1]".to_string()];
+    pairing.extend(["b[0][0]", "b[0][1]", "b[1][0]", "b[1][1]"].map(String::from));
+    pairing.extend(g1(&vk.alpha_g1).iter().map(Fq::to_string));
+    pairing.extend(g2(&-vk.beta_g2).iter().map(Fq::to_string));
+    pairing.extend(["vkX[0]", "vkX[1]"].map(String::from));
+    pairing.extend(g2(&-vk.gamma_g2).iter().map(Fq::to_string));
+    pairing.extend(["c[0]", "c[1]"].map(String::from));
+    pairing.extend(g2(&-vk.delta_g2).iter().map(Fq::to_string));
+    let pairing: String = pairing
+        .iter()
+        .enumerate()
+        .map(|(i, value)| format!("        input[{}] = {};\n", i, value))
+        .collect();
+
+    let [x, y] = g1(&vk.gamma_abc_g1[0]);
+    let mut vk_x = format!("        uint256[2] memory vkX = [uint256({}), {}];\n", x, y);
+    for (i, point) in vk.gamma_abc_g1[1..].iter().enumerate() {
+        let [x, y] = g1(point);
+        vk_x += &format!(
+            "        require(input_[{i}] < SNARK_SCALAR_FIELD, \"input not in field\");\n        \
+             vkX = ecAdd(vkX, ecMul([uint256({x}), {y}], input_[{i}]));\n"
+        );
+    }
+
+    Ok(format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+// Groth16 verifier over BN254 for verifying key {vk_id}.
+// Generated by `zk export-solidity`; do not edit.
+contract Verifier {{
+    uint256 constant SNARK_SCALAR_FIELD = {r};
+
+    function verifyProof(
+        uint[2] memory a,
+        uint[2][2] memory b,
+        uint[2] memory c,
+        uint[{inputs}] memory input_
+    ) public view returns (bool r) {{
+{vk_x}
+        uint256[24] memory input;
+{pairing}
+        uint256[1] memory out;
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x08, input, 768, out, 0x20)
+        }}
+        return success && out[0] == 1;
+    }}
+
+    function ecAdd(uint256[2] memory p1, uint256[2] memory p2) internal view returns (uint256[2] memory r) {{
+        uint256[4] memory input;
+        input[0] = p1[0];
+        input[1] = p1[1];
+        input[2] = p2[0];
+        input[3] = p2[1];
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x06, input, 0x80, r, 0x40)
+        }}
+        require(success, "ecAdd failed");
+    }}
+
+    function ecMul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {{
+        uint256[3] memory input;
+        input[0] = p[0];
+        input[1] = p[1];
+        input[2] = s;
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 0x07, input, 0x60, r, 0x40)
+        }}
+        require(success, "ecMul failed");
+    }}
+}}
+"#,
+        vk_id = vk_id(vk)?,
+        r = ArkFr::MODULUS,
+        inputs = vk.gamma_abc_g1.len() - 1,
+        vk_x = vk_x,
+        pairing = pairing,
+    ))
+}
+
+/// Arguments of `verifyProof` for `proof`: JSON arrays of `0x` hex strings
+/// separated by commas, as `snarkjs generatecall` prints them.
+pub fn calldata(proof: &Proof<Bn254>, public_inputs: &[ArkFr]) -> String {
+    let b = g2(&proof.b);
+    format!(
+        "{},[{},{}],{},{}",
+        quoted_hex(&g1(&proof.a)),
+        quoted_hex(&b[..2]),
+        quoted_hex(&b[2..]),
+        quoted_hex(&g1(&proof.c)),
+        quoted_hex(public_inputs)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_proof, generate_proving_key, public_inputs, verify_proof, MerkleTreeData};
+    use ark_bn254::Fq2;
+    use ark_std::test_rng;
+
+    /// Reads the call data back the way the precompiles would.
+    fn decode(calldata: &str) -> (Proof<Bn254>, Vec<ArkFr>) {
+        let args: Vec<serde_json::Value> =
+            serde_json::from_str(&format!("[{}]", calldata)).unwrap();
+        let fq = |value: &serde_json::Value| {
+            let bytes = hex::decode(&value.as_str().unwrap()[2..]).unwrap();
+            Fq::from_be_bytes_mod_order(&bytes)
+        };
+        let fq2 = |pair: &serde_json::Value| Fq2::new(fq(&pair[1]), fq(&pair[0]));
+        let g1 = |point: &serde_json::Value| G1Affine::new(fq(&point[0]), fq(&point[1]));
+        let proof = Proof {
+            a: g1(&args[0]),
+            b: G2Affine::new(fq2(&args[1][0]), fq2(&args[1][1])),
+            c: g1(&args[2]),
+        };
+        let inputs = args[3]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| {
+                ArkFr::from_be_bytes_mod_order(&hex::decode(&value.as_str().unwrap()[2..]).unwrap())
+            })
+            .collect();
+        (proof, inputs)
+    }
+
+    #[test]
+    fn test_calldata_round_trips() {
+        let mut rng = test_rng();
+        let pk = generate_proving_key(1, &mut rng).unwrap();
+        let data = MerkleTreeData {
+            leaf: ArkFr::from(3u64),
+            siblings: vec![ArkFr::from(4u64)],
+            path_bits: vec![false],
+        };
+        let proof = create_proof(&pk, &data, &mut rng).unwrap();
+        let inputs = public_inputs(&data).unwrap();
+
+        let (decoded, decoded_inputs) = decode(&calldata(&proof, &inputs));
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded_inputs, inputs);
+        assert!(verify_proof(&pk.vk, &decoded, &decoded_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_contract_embeds_key() {
+        let pk = generate_proving_key(1, &mut test_rng()).unwrap();
+        let contract = verifier_contract(&pk.vk).unwrap();
+
+        assert!(contract.contains("uint[1] memory input_"));
+        assert!(contract.contains(&vk_id(&pk.vk).unwrap()));
+        assert!(contract.contains(&pk.vk.alpha_g1.x.to_string()));
+        assert!(contract.contains(&(-pk.vk.delta_g2).y.c1.to_string()));
+        assert!(contract.contains("input[23] = "));
+    }
+}
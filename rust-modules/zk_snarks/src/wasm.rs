@@ -0,0 +1,99 @@
+//! wasm-bindgen entry points for proving membership in the browser.
+//!
+//! Built with `--features wasm`; randomness comes from `getrandom`'s `js`
+//! backend. Failures are thrown as `ProofError`, whose `code` matches the one
+//! the Fluence service reports.
+
+use ark_bn254::Bn254;
+use ark_groth16::ProvingKey;
+use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{parse_data, proof_coordinates, prove, ProofEnvelope, ZkError};
+
+/// Error thrown to JavaScript by the functions of this module.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct ProofError {
+    code: u32,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl ProofError {
+    /// Stable error code, see `ZkError::code`.
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+}
+
+impl From<ZkError> for ProofError {
+    fn from(e: ZkError) -> Self {
+        ProofError {
+            code: e.code(),
+            message: e.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MerklePath {
+    siblings: Vec<String>,
+    path_bits: Vec<bool>,
+}
+
+#[derive(Serialize)]
+struct ProofOutput {
+    envelope: ProofEnvelope,
+    coordinates: serde_json::Value,
+}
+
+/// Generates a Groth16 membership proof for `leaf_hex`.
+///
+/// `proving_key` is an uncompressed `ProvingKey<Bn254>` for the depth of the
+/// path, and `path_json` is `{"siblings": [hex, ...], "path_bits": [bool, ...]}`
+/// as produced by wasm-lib. The key comes from the caller, so its points are
+/// checked to be on the curve and in the prime-order subgroup.
+///
+/// Returns `{"envelope": ProofEnvelope, "coordinates": ...}` as JSON; the
+/// envelope names `prover` if given.
+#[wasm_bindgen]
+pub fn prove_membership(
+    proving_key: &[u8],
+    leaf_hex: &str,
+    path_json: &str,
+    prover: Option<String>,
+) -> Result<String, ProofError> {
+    let pk = ProvingKey::<Bn254>::deserialize_uncompressed(proving_key).map_err(ZkError::from)?;
+    let path: MerklePath = serde_json::from_str(path_json).map_err(|e| invalid("path", e))?;
+    let data = parse_data(leaf_hex, &path.siblings, &path.path_bits)?;
+
+    let envelope = prove(&pk, &data, &mut OsRng, &prover.unwrap_or_default())?;
+
+    let output = ProofOutput {
+        coordinates: proof_coordinates(&envelope.decode_proof()?),
+        envelope,
+    };
+    Ok(serde_json::to_string(&output).map_err(|e| invalid("output", e))?)
+}
+
+/// Re-encodes an envelope from `prove_membership` as CBOR, e.g. for storage.
+#[wasm_bindgen]
+pub fn envelope_to_cbor(envelope_json: &str) -> Result<Vec<u8>, ProofError> {
+    Ok(ProofEnvelope::from_json(envelope_json)?.to_cbor()?)
+}
+
+fn invalid(field: &'static str, reason: impl std::fmt::Display) -> ZkError {
+    ZkError::InvalidInput {
+        field,
+        reason: reason.to_string(),
+    }
+}
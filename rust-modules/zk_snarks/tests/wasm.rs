@@ -0,0 +1,53 @@
+//! Node tests for the wasm prover. Run with `wasm-pack test --node -- --features wasm`.
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde_json::Value;
+use wasm_bindgen_test::*;
+use zk_snarks::wasm::{envelope_to_cbor, prove_membership};
+use zk_snarks::{generate_proving_key, parse_data, public_inputs, ProofEnvelope};
+
+const LEAF: &str = "5dc83aa52097f90c9aa00a9ac5c455cbda815e4b3affcdb8f5ef1f2d98b2621b";
+const SIBLING: &str = "765643c6d057ce226c9ee9340bd86085eaba0a0030524c7f54cb03760495a30c";
+const PATH: &str = r#"{"siblings": ["765643c6d057ce226c9ee9340bd86085eaba0a0030524c7f54cb03760495a30c"], "path_bits": [true]}"#;
+
+#[wasm_bindgen_test]
+fn test_prove_membership_verifies() {
+    let pk = generate_proving_key(1, &mut ChaCha20Rng::seed_from_u64(1)).unwrap();
+    let mut pk_bytes = Vec::new();
+    pk.serialize_uncompressed(&mut pk_bytes).unwrap();
+
+    let output = prove_membership(&pk_bytes, LEAF, PATH, Some("browser".into())).unwrap();
+    let output: Value = serde_json::from_str(&output).unwrap();
+    let envelope_json = output["envelope"].to_string();
+    let envelope = ProofEnvelope::from_json(&envelope_json).unwrap();
+
+    let data = parse_data(LEAF, &[SIBLING.to_string()], &[true]).unwrap();
+    assert_eq!(
+        envelope.decode_public_inputs().unwrap(),
+        public_inputs(&data).unwrap()
+    );
+    assert_eq!(envelope.prover, "browser");
+    assert!(envelope.verify(&pk.vk).unwrap());
+
+    let cbor = envelope_to_cbor(&envelope_json).unwrap();
+    assert_eq!(ProofEnvelope::from_cbor(&cbor).unwrap(), envelope);
+}
+
+#[wasm_bindgen_test]
+fn test_prove_membership_rejects_bad_input() {
+    let mismatched = r#"{"siblings": ["00"], "path_bits": [true, false]}"#;
+    let not_hex = r#"{"siblings": ["zz"], "path_bits": [true]}"#;
+
+    assert!(prove_membership(&[], LEAF, PATH, None).is_err());
+
+    let pk = generate_proving_key(1, &mut ChaCha20Rng::seed_from_u64(3)).unwrap();
+    let mut pk_bytes = Vec::new();
+    pk.serialize_uncompressed(&mut pk_bytes).unwrap();
+    let err = prove_membership(&pk_bytes, LEAF, mismatched, None).unwrap_err();
+    assert_eq!(err.code(), 2);
+    let err = prove_membership(&pk_bytes, LEAF, not_hex, None).unwrap_err();
+    assert_eq!(err.code(), 1);
+}